@@ -0,0 +1,58 @@
+use std::hint::black_box;
+
+use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group, criterion_main};
+use magwords::words::ServerMessage;
+use magwords::words::codec::Codec;
+use magwords::words::frame::Frame;
+
+/// Poets on a busy fridge, who all get every move.
+const POETS: usize = 500;
+
+fn move_message() -> ServerMessage {
+    serde_json::from_str(r#"{"type":"move","data":{"id":3,"v":7,"x":10,"y":20}}"#).unwrap()
+}
+
+/// What a move costs to send to every poet, encoded for each of them, or once per codec.
+fn broadcast(criterion: &mut Criterion) {
+    let message = move_message();
+
+    let mut group = criterion.benchmark_group("broadcast");
+
+    for codec in [Codec::Json, Codec::MessagePack] {
+        let codec_name = format!("{:?}", codec);
+
+        group.bench_function(
+            BenchmarkId::new("encoded per poet", &codec_name),
+            |bencher| {
+                bencher.iter_batched(
+                    || message.clone(),
+                    |message| {
+                        for _ in 0..POETS {
+                            black_box(codec.encode(&message).unwrap());
+                        }
+                    },
+                    BatchSize::SmallInput,
+                );
+            },
+        );
+
+        group.bench_function(BenchmarkId::new("encoded once", &codec_name), |bencher| {
+            bencher.iter_batched(
+                || message.clone(),
+                |message| {
+                    let frame = Frame::new(message);
+
+                    for _ in 0..POETS {
+                        black_box(frame.encoded(codec).unwrap());
+                    }
+                },
+                BatchSize::SmallInput,
+            );
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, broadcast);
+criterion_main!(benches);
@@ -1,16 +1,311 @@
-use clap::Parser;
+mod config_file;
+
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+
+use clap::{CommandFactory as _, FromArgMatches as _, Parser};
+use color_eyre::eyre;
+use ipnet::IpNet;
+use serde::{Serialize, Serializer};
 use tracing::{Level, event};
 
-#[derive(Parser, Debug)]
+use crate::states::config::{BindAddress, FridgePacks};
+use crate::utils::forwarded::parse_trusted_proxy;
+
+#[derive(Parser, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct Cli {
+    /// TOML file with options named after their long flag, like `max-poets = 500`.
+    /// Options from the environment and the command line take precedence.
+    #[clap(env = "CONFIG_FILE", long = "config")]
+    #[serde(skip)]
+    pub config: Option<PathBuf>,
+
+    /// Print the effective configuration, as a config file, and exit.
+    #[clap(long)]
+    #[serde(skip)]
+    pub print_config: bool,
+
+    /// What to log, like `info,magwords=debug`, in the syntax of `tracing_subscriber`'s `EnvFilter`.
+    /// Defaults to `debug`, and `trace` for magwords itself.
+    #[clap(env = "RUST_LOG", long)]
+    pub log_filter: Option<String>,
+
+    /// Addresses to listen on, like `0.0.0.0:3000` or `[::]:3000`, a Unix socket like `unix:/run/magwords.sock`,
+    /// or `systemd` for the sockets systemd passes on, comma separated for more than one.
+    /// A Unix socket is for a proxy on this machine, which is trusted like a `--trusted-proxies` one.
+    #[clap(
+        env = "BIND_TO",
+        long = "bind",
+        value_delimiter = ',',
+        default_value = "0.0.0.0:3000",
+        value_parser = parse_bind_address
+    )]
+    #[serde(rename = "bind")]
+    pub bind_to: Vec<BindAddress>,
+
     #[clap(env, long, default_value_t = 990)]
     pub fridge_width: u32,
 
     #[clap(env, long, default_value_t = 1600)]
     pub fridge_height: u32,
+
+    /// Directory in which the fridge layout is persisted. When unset, the layout is lost on restart.
+    #[clap(env, long)]
+    pub snapshot_dir: Option<PathBuf>,
+
+    /// Seconds between two snapshots of the fridge layout.
+    #[clap(env, long, default_value_t = 60)]
+    pub snapshot_interval: u64,
+
+    /// Seconds a fridge other than the default one may sit without poets before it's evicted.
+    #[clap(env, long, default_value_t = 900)]
+    pub fridge_idle_timeout: u64,
+
+    /// Seconds between two heartbeats, which also expire the grabs of poets who went quiet.
+    #[clap(env, long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    pub heartbeat_interval: u64,
+
+    /// Seconds a poet may take to answer a heartbeat before they're considered gone.
+    #[clap(env, long, default_value_t = 5)]
+    pub pong_timeout: u64,
+
+    /// Maximum number of fridges served at once, the default one included.
+    #[clap(env, long, default_value_t = 64)]
+    pub max_fridges: usize,
+
+    /// Word list files, or directories of `.txt` word list files, one word per line,
+    /// optionally followed by `*<count>`, and grouped in packs by `[<pack>]` lines. Defaults to the bundled word list.
+    #[clap(env, long, value_delimiter = ',')]
+    pub word_list: Vec<PathBuf>,
+
+    /// Word packs on fridges that aren't listed in `--fridge-packs`. Defaults to all packs.
+    #[clap(env, long, value_delimiter = ',')]
+    pub packs: Vec<String>,
+
+    /// Word packs on a specific fridge, as `<fridge>=<pack>+<pack>`.
+    #[clap(env, long, value_delimiter = ',', value_parser = parse_fridge_packs)]
+    pub fridge_packs: Vec<FridgePacks>,
+
+    /// Moves remembered per fridge, so that it can be reverted to an earlier time.
+    #[clap(env, long, default_value_t = 10_000)]
+    pub history_size: usize,
+
+    /// Messages buffered per fridge for poets who fall behind, who get a fresh copy of the fridge
+    /// when they fall further behind than that.
+    #[clap(env, long, default_value_t = NonZeroUsize::new(256).unwrap())]
+    pub broadcast_capacity: NonZeroUsize,
+
+    /// Moves a poet may make per second, on average. Faster moves are dropped. Grabbing and letting go
+    /// of a word count as moves too, and so does each move undone or redone.
+    #[clap(env, long, default_value_t = 10.0, value_parser = parse_rate)]
+    pub move_rate: f64,
+
+    /// Moves a poet may make in quick succession, before `--move-rate` kicks in.
+    #[clap(env, long, default_value_t = 30)]
+    pub move_burst: u32,
+
+    /// Moves all poets at the same address may make per second together. When unset, addresses aren't limited.
+    #[clap(env, long, value_parser = parse_rate)]
+    pub address_move_rate: Option<f64>,
+
+    /// Moves all poets at the same address may make in quick succession, before `--address-move-rate` kicks in.
+    #[clap(env, long, default_value_t = 100)]
+    pub address_move_burst: u32,
+
+    /// Moves a poet may have dropped in a minute for going too fast, before they're disconnected.
+    #[clap(env, long, default_value_t = 60)]
+    pub max_dropped_moves: u32,
+
+    /// Poets connected at once, across all fridges. Poets beyond that are turned away.
+    #[clap(env, long, default_value_t = 1000)]
+    pub max_poets: usize,
+
+    /// Poets connected at once from the same address.
+    #[clap(env, long, default_value_t = 20)]
+    pub max_connections_per_address: usize,
+
+    /// Networks (like `10.0.0.0/8`) or addresses of reverse proxies whose `Forwarded`, `X-Forwarded-For`
+    /// and `X-Real-IP` headers tell us who a poet is. A proxy on a Unix `--bind` socket is always trusted.
+    #[clap(env, long, value_delimiter = ',', value_parser = parse_trusted_proxy)]
+    pub trusted_proxies: Vec<IpNet>,
+
+    /// PEM certificate chain to serve HTTPS and WSS with, instead of plain HTTP and WS.
+    /// Reloaded, along with the key, when either file changes.
+    #[clap(env, long)]
+    pub tls_certificate: Option<PathBuf>,
+
+    /// PEM private key of `--tls-certificate`.
+    #[clap(env, long)]
+    pub tls_key: Option<PathBuf>,
+
+    /// Addresses to listen on for plain HTTP, which is redirected to HTTPS on the port of the first TCP `--bind` address.
+    #[clap(
+        env = "HTTP_REDIRECT_BIND_TO",
+        long = "http-redirect-bind",
+        value_delimiter = ',',
+        value_parser = parse_bind_address
+    )]
+    #[serde(rename = "http-redirect-bind")]
+    pub http_redirect_bind_to: Vec<BindAddress>,
+
+    /// Bearer token required by the admin endpoints. When unset, they're disabled.
+    #[clap(env, long, hide_env_values = true)]
+    #[serde(serialize_with = "redact")]
+    pub admin_token: Option<String>,
+
+    /// Bearer token required to move words over the REST API. When unset, the REST API is read-only.
+    #[clap(env, long, hide_env_values = true)]
+    #[serde(serialize_with = "redact")]
+    pub api_token: Option<String>,
+}
+
+#[expect(
+    clippy::ref_option,
+    reason = "serde's `serialize_with` wants a reference"
+)]
+fn redact<S: Serializer>(token: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    token.as_ref().map(|_| "<redacted>").serialize(serializer)
+}
+
+fn parse_bind_address(value: &str) -> Result<BindAddress, String> {
+    if value == "systemd" {
+        return Ok(BindAddress::Systemd);
+    }
+
+    if let Some(path) = value.strip_prefix("unix:") {
+        if path.is_empty() {
+            return Err("expected a path after `unix:`".to_owned());
+        }
+
+        return Ok(BindAddress::Unix(path.into()));
+    }
+
+    value.parse().map(BindAddress::Tcp).map_err(|_| {
+        format!(
+            "expected an address like `0.0.0.0:3000` or `[::]:3000`, `unix:<path>`, or `systemd`, got `{}`",
+            value
+        )
+    })
+}
+
+fn parse_fridge_packs(value: &str) -> Result<FridgePacks, String> {
+    let Some((fridge, packs)) = value.split_once('=') else {
+        return Err(format!(
+            "expected `<fridge>=<pack>+<pack>`, got `{}`",
+            value
+        ));
+    };
+
+    let packs = packs.split('+').map(str::to_owned).collect::<Vec<_>>();
+
+    if fridge.is_empty() || packs.iter().any(String::is_empty) {
+        return Err(format!(
+            "expected `<fridge>=<pack>+<pack>`, got `{}`",
+            value
+        ));
+    }
+
+    Ok(FridgePacks {
+        fridge: fridge.to_owned(),
+        packs,
+    })
 }
+
+fn parse_rate(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate > 0.0 => Ok(rate),
+        Ok(_) | Err(_) => Err(format!("expected a positive number, got `{}`", value)),
+    }
+}
+
 impl Cli {
+    /// Parses the command line and the environment, on top of the config file from `--config`, if any.
+    ///
+    /// # Errors
+    /// * The config file can't be read, or isn't valid
+    pub fn load() -> Result<Self, eyre::Report> {
+        let matches = Self::command().get_matches();
+
+        let Some(path) = matches.get_one::<PathBuf>("config") else {
+            return Ok(Self::from_arg_matches(&matches).unwrap_or_else(|error| error.exit()));
+        };
+
+        let matches = config_file::with_defaults(Self::command(), path)?.get_matches();
+
+        Ok(Self::from_arg_matches(&matches).unwrap_or_else(|error| error.exit()))
+    }
+
+    /// Like [`Cli::load`], for when we're running already: mistakes are returned, rather than printed before exiting.
+    ///
+    /// # Errors
+    /// * The config file can't be read, or isn't valid
+    /// * An option from the config file isn't valid
+    pub fn reload() -> Result<Self, eyre::Report> {
+        let mut matches = Self::command().try_get_matches()?;
+
+        if let Some(path) = matches.get_one::<PathBuf>("config").cloned() {
+            matches = config_file::with_defaults(Self::command(), &path)?.try_get_matches()?;
+        }
+
+        Ok(Self::from_arg_matches(&matches)?)
+    }
+
+    /// The effective configuration, as a config file, with tokens redacted.
+    ///
+    /// # Errors
+    /// * An option can't be written as TOML, which is a bug
+    pub fn to_toml(&self) -> Result<String, eyre::Report> {
+        Ok(toml::to_string(self)?)
+    }
+
     pub fn print(&self) {
+        if let Some(config) = self.config.as_deref() {
+            event!(Level::INFO, config = %config.display(), "Config file");
+        }
+
+        if let Some(log_filter) = self.log_filter.as_deref() {
+            event!(Level::INFO, log_filter, "Log filter");
+        }
+
+        event!(Level::INFO, bind_to = ?self.bind_to, "Listeners");
+
+        if let Some(tls_certificate) = self.tls_certificate.as_deref() {
+            event!(Level::INFO, tls_certificate = %tls_certificate.display(), http_redirect_bind_to = ?self.http_redirect_bind_to, "TLS");
+        }
+
         event!(Level::INFO, fridge_width = %self.fridge_width, fridge_height = %self.fridge_height, "Fridge dimensions");
+
+        if let Some(snapshot_dir) = self.snapshot_dir.as_deref() {
+            event!(Level::INFO, snapshot_dir = %snapshot_dir.display(), snapshot_interval = %self.snapshot_interval, "Snapshots");
+        }
+
+        if !self.word_list.is_empty() {
+            event!(Level::INFO, word_list = ?self.word_list, "Word lists");
+        }
+
+        if !self.packs.is_empty() || !self.fridge_packs.is_empty() {
+            event!(Level::INFO, packs = ?self.packs, fridge_packs = ?self.fridge_packs, "Word packs");
+        }
+
+        event!(Level::INFO, fridge_idle_timeout = %self.fridge_idle_timeout, max_fridges = %self.max_fridges, history_size = %self.history_size, broadcast_capacity = %self.broadcast_capacity, "Fridges");
+
+        event!(Level::INFO, heartbeat_interval = %self.heartbeat_interval, pong_timeout = %self.pong_timeout, "Heartbeat");
+
+        event!(Level::INFO, move_rate = %self.move_rate, move_burst = %self.move_burst, address_move_rate = ?self.address_move_rate, address_move_burst = %self.address_move_burst, max_dropped_moves = %self.max_dropped_moves, "Move limits");
+
+        event!(Level::INFO, max_poets = %self.max_poets, max_connections_per_address = %self.max_connections_per_address, trusted_proxies = ?self.trusted_proxies, "Connection limits");
+
+        event!(
+            Level::INFO,
+            enabled = self.admin_token.is_some(),
+            "Admin endpoints"
+        );
+
+        event!(
+            Level::INFO,
+            enabled = self.api_token.is_some(),
+            "Moving words over the REST API"
+        );
     }
 }
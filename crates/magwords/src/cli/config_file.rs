@@ -0,0 +1,185 @@
+use std::path::Path;
+
+use clap::Command;
+use color_eyre::eyre::{self, Context as _};
+use toml::{Table, Value};
+
+/// Uses the options in the TOML file at `path` as the defaults of `command`,
+/// so that the environment and the command line take precedence.
+///
+/// # Errors
+/// * The file can't be read, isn't TOML, or has options we don't know or that don't take a value
+pub fn with_defaults(command: Command, path: &Path) -> Result<Command, eyre::Report> {
+    let contents = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("Failed to read config file {}", path.display()))?;
+
+    defaults(command, &contents).wrap_err_with(|| format!("Invalid config file {}", path.display()))
+}
+
+/// Options are named like their long flag, `max-poets = 500` or `max_poets = 500` alike.
+fn defaults(mut command: Command, contents: &str) -> Result<Command, eyre::Report> {
+    let table = contents.parse::<Table>()?;
+
+    for (key, value) in table {
+        let long = key.replace('_', "-");
+
+        let Some(arg) = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(long.as_str()))
+        else {
+            return Err(eyre::Report::msg(format!("Unknown option `{}`", key)));
+        };
+
+        if !arg.get_action().takes_values() || long == "config" {
+            return Err(eyre::Report::msg(format!(
+                "`{}` can't be set in a config file",
+                key
+            )));
+        }
+
+        let id = arg.get_id().clone();
+
+        let values = values(&key, value)?;
+
+        command = command.mut_arg(id, |arg| {
+            // tokens shouldn't show up in `--help`
+            let hide = arg.is_hide_env_values_set();
+
+            arg.default_values(values).hide_default_value(hide)
+        });
+    }
+
+    Ok(command)
+}
+
+fn values(key: &str, value: Value) -> Result<Vec<String>, eyre::Report> {
+    match value {
+        Value::Array(values) => values.into_iter().map(|value| scalar(key, value)).collect(),
+        value @ (Value::String(_)
+        | Value::Integer(_)
+        | Value::Float(_)
+        | Value::Boolean(_)
+        | Value::Datetime(_)
+        | Value::Table(_)) => Ok(vec![scalar(key, value)?]),
+    }
+}
+
+fn scalar(key: &str, value: Value) -> Result<String, eyre::Report> {
+    match value {
+        Value::String(value) => Ok(value),
+        Value::Integer(value) => Ok(value.to_string()),
+        Value::Float(value) => Ok(value.to_string()),
+        Value::Boolean(value) => Ok(value.to_string()),
+        Value::Datetime(_) | Value::Array(_) | Value::Table(_) => Err(eyre::Report::msg(format!(
+            "`{}` should be a string, a number, or a list of those",
+            key
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use clap::{CommandFactory as _, FromArgMatches as _};
+    use pretty_assertions::assert_eq;
+
+    use crate::build_configs;
+    use crate::cli::Cli;
+    use crate::cli::config_file::defaults;
+    use crate::states::config::BindAddress;
+
+    fn load(contents: &str, args: &[&str]) -> Cli {
+        let command = defaults(Cli::command(), contents).unwrap();
+
+        let matches = command
+            .try_get_matches_from(std::iter::once("magwords").chain(args.iter().copied()))
+            .unwrap();
+
+        Cli::from_arg_matches(&matches).unwrap()
+    }
+
+    #[test]
+    fn command_line_overrides_file() {
+        let contents = r#"
+            bind = ["127.0.0.1:4000", "unix:/run/magwords.sock"]
+            max-poets = 500
+            move_rate = 2.5
+            word-list = "words.txt"
+        "#;
+
+        let cli = load(contents, &["--max-poets", "20"]);
+
+        assert_eq!(
+            cli.bind_to,
+            vec![
+                BindAddress::Tcp("127.0.0.1:4000".parse().unwrap()),
+                BindAddress::Unix("/run/magwords.sock".into()),
+            ],
+            "lists from the file"
+        );
+        assert_eq!(cli.max_poets, 20, "command line wins");
+        assert!((cli.move_rate - 2.5).abs() < f64::EPSILON, "from the file");
+        assert_eq!(
+            cli.word_list,
+            vec![PathBuf::from("words.txt")],
+            "one or a list"
+        );
+        assert_eq!(cli.fridge_width, 990, "default");
+    }
+
+    #[test]
+    fn tls_from_the_file_with_a_redirect_from_the_command_line() {
+        let contents = r#"
+            tls-certificate = "cert.pem"
+            tls-key = "key.pem"
+        "#;
+
+        let cli = load(contents, &["--http-redirect-bind", "0.0.0.0:8080"]);
+
+        let tls = build_configs(&cli).unwrap().tls;
+
+        assert_eq!(
+            tls.map(|tls| tls.redirect_from),
+            Some(vec![BindAddress::Tcp("0.0.0.0:8080".parse().unwrap())]),
+            "redirects to the TLS from the file"
+        );
+    }
+
+    #[test]
+    fn rejects_a_redirect_without_tls() {
+        let cli = load(r#"http-redirect-bind = "0.0.0.0:8080""#, &[]);
+
+        assert_eq!(
+            build_configs(&cli).ok().map(|_| ()),
+            None,
+            "nothing to redirect to"
+        );
+
+        let cli = load(r#"tls-key = "key.pem""#, &[]);
+
+        assert_eq!(
+            build_configs(&cli).ok().map(|_| ()),
+            None,
+            "a key without a certificate"
+        );
+    }
+
+    #[test]
+    fn rejects_what_it_doesnt_know() {
+        for contents in [
+            "max-poet = 5",
+            "print-config = true",
+            "config = \"other.toml\"",
+            "[fridge]\nwidth = 5",
+            "max-poets = ",
+        ] {
+            assert_eq!(
+                defaults(Cli::command(), contents).ok().map(|_| ()),
+                None,
+                "{}",
+                contents
+            );
+        }
+    }
+}
@@ -0,0 +1,402 @@
+#![expect(
+    clippy::must_use_candidate,
+    reason = "The library is only there for the benches, it's not an API"
+)]
+
+mod build_env;
+mod cli;
+mod log_filter;
+mod router;
+mod routes;
+mod server;
+mod signal_handlers;
+mod span;
+mod state;
+mod states;
+mod tasks;
+mod utils;
+pub mod words;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use color_eyre::config::HookBuilder;
+use color_eyre::eyre::{self, Context as _};
+use states::config::Config;
+use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use tracing::{Level, event};
+use tracing_subscriber::Layer as _;
+use tracing_subscriber::layer::SubscriberExt as _;
+use tracing_subscriber::reload;
+use tracing_subscriber::util::SubscriberInitExt as _;
+
+use crate::build_env::get_build_env;
+use crate::cli::Cli;
+use crate::log_filter::LogFilter;
+use crate::router::build_router;
+use crate::server::setup_server;
+use crate::state::ApplicationState;
+use crate::states::config::{FridgeDimensions, MoveLimits, RateLimit, SnapshotConfig, TlsConfig};
+use crate::utils::flatten_handle;
+use crate::words::fridges::Fridges;
+use crate::words::word_list;
+
+fn build_configs(args: &Cli) -> Result<Config, eyre::Report> {
+    // not clap's `requires`, which takes the options from the config file for defaults, and skips them
+    if args.tls_certificate.is_some() != args.tls_key.is_some() {
+        return Err(eyre::Report::msg(
+            "Either both or neither of `tls-certificate` and `tls-key` should be set",
+        ));
+    }
+
+    if args.tls_certificate.is_none() && !args.http_redirect_bind_to.is_empty() {
+        return Err(eyre::Report::msg(
+            "`http-redirect-bind` redirects to HTTPS, which needs `tls-certificate` and `tls-key`",
+        ));
+    }
+
+    // a poet has until the heartbeat after the next one to answer, at the latest
+    if args.pong_timeout <= args.heartbeat_interval {
+        return Err(eyre::Report::msg(format!(
+            "`pong-timeout` ({}s) should be longer than `heartbeat-interval` ({}s)",
+            args.pong_timeout, args.heartbeat_interval
+        )));
+    }
+
+    let config =
+        Config {
+            bind_to: args.bind_to.clone(),
+            tls: args.tls_certificate.clone().zip(args.tls_key.clone()).map(
+                |(certificate, key)| TlsConfig {
+                    certificate,
+                    key,
+                    redirect_from: args.http_redirect_bind_to.clone(),
+                },
+            ),
+            fridge_dimensions: FridgeDimensions {
+                fridge_width: args.fridge_width,
+                fridge_height: args.fridge_height,
+            },
+            snapshot: args.snapshot_dir.clone().map(|directory| SnapshotConfig {
+                directory,
+                interval: Duration::from_secs(args.snapshot_interval),
+            }),
+            fridge_idle_timeout: Duration::from_secs(args.fridge_idle_timeout),
+            heartbeat_interval: Duration::from_secs(args.heartbeat_interval),
+            pong_timeout: Duration::from_secs(args.pong_timeout),
+            max_fridges: args.max_fridges,
+            word_lists: args.word_list.clone(),
+            packs: args.packs.clone(),
+            fridge_packs: args.fridge_packs.clone(),
+            history_size: args.history_size,
+            broadcast_capacity: args.broadcast_capacity.get(),
+            move_limits: MoveLimits {
+                poet: RateLimit {
+                    per_second: args.move_rate,
+                    burst: args.move_burst,
+                },
+                address: args.address_move_rate.map(|per_second| RateLimit {
+                    per_second,
+                    burst: args.address_move_burst,
+                }),
+                max_dropped: args.max_dropped_moves,
+            },
+            max_poets: args.max_poets,
+            max_connections_per_address: args.max_connections_per_address,
+            trusted_proxies: args.trusted_proxies.clone(),
+            admin_token: args.admin_token.clone(),
+            api_token: args.api_token.clone(),
+        };
+
+    Ok(config)
+}
+
+/// Builds the fridge registry, restoring the default fridge from its snapshot, if any.
+async fn build_fridges(config: &Config) -> Result<Arc<Fridges>, eyre::Report> {
+    let word_packs = word_list::load_word_packs(&config.word_lists).await?;
+
+    if let Some(snapshot_config) = config.snapshot.as_ref() {
+        tokio::fs::create_dir_all(&snapshot_config.directory)
+            .await
+            .wrap_err("Failed to create snapshot directory")?;
+    }
+
+    let fridges = Fridges::new(&word_packs, config).await?;
+
+    Ok(Arc::new(fridges))
+}
+
+fn print_header() {
+    const NAME: &str = env!("CARGO_PKG_NAME");
+    const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+    let build_env = get_build_env();
+
+    event!(
+        Level::INFO,
+        "{} v{} - built for {} ({})",
+        NAME,
+        VERSION,
+        build_env.get_target(),
+        build_env.get_target_cpu().unwrap_or("base cpu variant"),
+    );
+}
+
+/// Starts all the tasks, such as the web server, the key refresh, and
+/// ensures all tasks are gracefully shutdown in case of error, ctrl-c or `SIGTERM`.
+async fn start_tasks(log_filter: LogFilter) -> Result<(), eyre::Report> {
+    let args = cli::Cli::load()?;
+
+    let config = build_configs(&args)?;
+
+    log_filter.set(log_filter::parse(args.log_filter.as_deref())?)?;
+
+    if args.print_config {
+        print!("{}", args.to_toml()?);
+
+        return Ok(());
+    }
+
+    print_header();
+    args.print();
+
+    // this channel is used to communicate between
+    // tasks and this function, in the case that a task fails, they'll send a message on the shutdown channel
+    // after which we'll gracefully terminate other services
+    let token = CancellationToken::new();
+
+    let fridges = build_fridges(&config).await?;
+
+    let heartbeat_interval = config.heartbeat_interval;
+
+    let application_state = ApplicationState::new(config, Arc::clone(&fridges));
+
+    let tasks = TaskTracker::new();
+
+    if let Some(snapshot_config) = application_state.config.snapshot.as_ref() {
+        let token = token.clone();
+        let fridges = Arc::clone(&fridges);
+        let period = snapshot_config.interval;
+
+        tasks.spawn(async move {
+            let _guard = token.clone().drop_guard();
+
+            tasks::snapshot_fridges(fridges, period, token).await;
+        });
+    }
+
+    {
+        let token = token.clone();
+        let fridges = Arc::clone(&fridges);
+
+        tasks.spawn(async move {
+            let _guard = token.clone().drop_guard();
+
+            tasks::evict_idle_fridges(fridges, token).await;
+        });
+    }
+
+    {
+        let token = token.clone();
+        let fridges = Arc::clone(&fridges);
+
+        tasks.spawn(async move {
+            let _guard = token.clone().drop_guard();
+
+            tasks::stream_drags(fridges, token).await;
+        });
+    }
+
+    {
+        let token = token.clone();
+        let fridges = Arc::clone(&fridges);
+        let connections = Arc::clone(&application_state.connections);
+        let config = Arc::clone(&application_state.config);
+
+        tasks.spawn(async move {
+            let _guard = token.clone().drop_guard();
+
+            tasks::reload_on_hangup(fridges, connections, log_filter, config, token).await;
+        });
+    }
+
+    {
+        let token = token.clone();
+
+        let config = Arc::clone(&application_state.config);
+        let router = build_router(application_state);
+
+        tasks.spawn(async move {
+            let _guard = token.clone().drop_guard();
+
+            let server =
+                setup_server(&config.bind_to, config.tls.as_ref(), router, token.clone()).await;
+
+            match server {
+                Ok(()) => {
+                    event!(Level::INFO, "Server shutting down gracefully");
+                },
+                Err(error) => {
+                    event!(Level::ERROR, ?error, "Server shutting down");
+                },
+            }
+        });
+    }
+
+    {
+        let token = token.clone();
+        let fridges = Arc::clone(&fridges);
+
+        tasks.spawn(async move {
+            let _guard = token.clone().drop_guard();
+
+            tasks::heartbeat(fridges, heartbeat_interval, token).await;
+        });
+    };
+
+    wait_for_shutdown(&token).await;
+
+    // announce cancel
+    token.cancel();
+
+    // close the tracker, otherwise wait doesn't work
+    tasks.close();
+
+    // wait for the task that holds the server to exit gracefully
+    // it listens to shutdown_send
+    if timeout(Duration::from_secs(10), tasks.wait())
+        .await
+        .is_err()
+    {
+        event!(Level::ERROR, "Tasks didn't stop within allotted time!");
+    }
+
+    event!(Level::INFO, message = "Goodbye");
+
+    Ok(())
+}
+
+/// Waits for a signal to shut down, or for a task to stop.
+async fn wait_for_shutdown(token: &CancellationToken) {
+    // now we wait forever for either
+    // * SIGTERM
+    // * ctrl + c (SIGINT)
+    // * a message on the shutdown channel, sent either by the server task or
+    // another task when they complete (which means they failed)
+    tokio::select! {
+        result = signal_handlers::wait_for_sigterm() => {
+            if let Err(error) = result {
+                event!(Level::ERROR, ?error, "Failed to register SIGERM handler, aborting");
+            } else {
+                // we completed because ...
+                event!(Level::WARN, "Sigterm detected, stopping all tasks");
+            }
+        },
+        result = signal_handlers::wait_for_sigint() => {
+            if let Err(error) = result {
+                event!(Level::ERROR, ?error, "Failed to register CTRL+C handler, aborting");
+            } else {
+                // we completed because ...
+                event!(Level::WARN, "CTRL+C detected, stopping all tasks");
+            }
+        },
+        () = token.cancelled() => {
+            event!(Level::WARN, "Underlying task stopped, stopping all others tasks");
+        },
+    }
+}
+
+/// Logs with the default filter, until the configured one is set on the returned [`LogFilter`].
+fn init_tracing() -> Result<LogFilter, eyre::Report> {
+    let (filter, handle) = reload::Layer::new(log_filter::build_default_filter());
+
+    let registry = tracing_subscriber::registry();
+
+    #[cfg(feature = "tokio-console")]
+    let registry = registry.with(console_subscriber::ConsoleLayer::builder().spawn());
+
+    registry
+        .with(tracing_subscriber::fmt::layer().with_filter(filter))
+        .with(tracing_error::ErrorLayer::default())
+        .try_init()?;
+
+    Ok(LogFilter::new(handle))
+}
+
+/// Takes the variables systemd passes listeners with out of the environment, before [`run`] starts.
+///
+/// # Safety
+/// No other thread may be running, which could be reading the environment meanwhile.
+#[cfg(unix)]
+pub unsafe fn take_systemd_environment() {
+    // SAFETY: the caller makes sure no other thread is running
+    unsafe {
+        server::systemd::take_environment();
+    }
+}
+
+/// Runs the server until it's told to stop.
+///
+/// # Errors
+/// * The configuration is invalid
+/// * A task failed
+///
+/// # Panics
+/// * The runtime can't be built
+pub fn run() -> Result<(), eyre::Report> {
+    HookBuilder::default()
+        .capture_span_trace_by_default(true)
+        .display_env_section(false)
+        .install()?;
+
+    let log_filter = init_tracing()?;
+
+    // initialize the runtime
+    let result: Result<(), eyre::Report> = tokio::runtime::Builder::new_multi_thread()
+        .enable_io()
+        .enable_time()
+        .build()
+        .expect("Failed building the Runtime")
+        .block_on(async {
+            // explicitly launch everything in a spawned task
+            // see https://docs.rs/tokio/latest/tokio/attr.main.html#non-worker-async-function
+            let handle = tokio::task::spawn(start_tasks(log_filter));
+
+            flatten_handle(handle).await
+        });
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser as _;
+    use pretty_assertions::assert_eq;
+
+    use crate::build_configs;
+    use crate::cli::Cli;
+
+    #[test]
+    fn pong_timeout_outlasts_heartbeat_interval() {
+        let config = |args: &[&str]| {
+            let cli = Cli::try_parse_from(std::iter::once("magwords").chain(args.iter().copied()))
+                .unwrap();
+
+            build_configs(&cli).ok().map(|_| ())
+        };
+
+        assert_eq!(config(&[]), Some(()), "defaults");
+        assert_eq!(
+            config(&["--heartbeat-interval", "10"]),
+            None,
+            "heartbeats further apart than the pong timeout"
+        );
+        assert_eq!(
+            config(&["--heartbeat-interval", "10", "--pong-timeout", "30"]),
+            Some(()),
+            "pong timeout raised along"
+        );
+    }
+}
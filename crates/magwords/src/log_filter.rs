@@ -0,0 +1,44 @@
+use color_eyre::eyre::{self, Context as _};
+use tracing_subscriber::filter::EnvFilter;
+use tracing_subscriber::reload;
+
+/// Swaps the filter of the log output, for the one configured once the configuration is loaded, and on every reload.
+pub struct LogFilter {
+    reload: Box<dyn Fn(EnvFilter) -> Result<(), reload::Error> + Send + Sync>,
+}
+
+impl LogFilter {
+    pub fn new<S: 'static>(handle: reload::Handle<EnvFilter, S>) -> Self {
+        Self {
+            reload: Box::new(move |filter| handle.reload(filter)),
+        }
+    }
+
+    /// # Errors
+    /// * The log output is gone, which is a bug
+    pub fn set(&self, filter: EnvFilter) -> Result<(), eyre::Report> {
+        (self.reload)(filter).wrap_err("Failed to change the log filter")
+    }
+}
+
+/// The filter for `directives`, or the default one without.
+///
+/// # Errors
+/// * `directives` isn't a valid filter
+pub fn parse(directives: Option<&str>) -> Result<EnvFilter, eyre::Report> {
+    match directives {
+        Some(directives) => EnvFilter::builder()
+            .parse(directives)
+            .wrap_err_with(|| format!("Invalid log filter `{}`", directives)),
+        None => Ok(build_default_filter()),
+    }
+}
+
+pub fn build_default_filter() -> EnvFilter {
+    EnvFilter::builder()
+        .parse(format!(
+            "DEBUG,{}=TRACE,tower_http::trace=TRACE",
+            env!("CARGO_CRATE_NAME")
+        ))
+        .expect("Default filter should always work")
+}
@@ -26,11 +26,16 @@ async fn healthz() -> impl IntoResponse {
 }
 
 pub fn build_router(state: ApplicationState) -> Router {
+    let make_span = MakeSpanWithUuid::new()
+        .level(Level::INFO)
+        .trusted_proxies(&state.config.trusted_proxies);
+
     let api_router = build_api_router(state.clone());
     let html_router = build_html_router();
 
     let ws_router = Router::new()
         .route("/ws", get(words::ws_handler))
+        .route("/ws/{fridge}", get(words::fridge_ws_handler))
         .with_state(state);
 
     // we can move the `/healthz` layer beneath the `TraceLayer` to prevent it from being logged
@@ -45,7 +50,7 @@ pub fn build_router(state: ApplicationState) -> Router {
         .layer(CorsLayer::permissive())
         .layer(
             TraceLayer::new_for_http()
-                .make_span_with(MakeSpanWithUuid::new().level(Level::INFO))
+                .make_span_with(make_span)
                 .on_request(DefaultOnRequest::new().level(Level::TRACE))
                 .on_response(DefaultOnResponse::new().level(Level::INFO)),
         )
@@ -1,7 +1,24 @@
+mod auth;
+mod events;
+mod fridge;
+mod words;
+
 use axum::Router;
+use axum::routing::{get, post};
 
 use crate::state::ApplicationState;
 
 pub fn build_api_router(state: ApplicationState) -> Router {
-    Router::new().with_state(state)
+    Router::new()
+        .route("/config", get(words::config))
+        .route("/events", get(events::events))
+        .route("/events/move", post(events::submit_move))
+        .route("/fridge/replay", get(fridge::replay))
+        .route("/fridge/revert", post(fridge::revert))
+        .route("/poets", get(words::poets))
+        .route("/words", get(words::words))
+        .route("/words/move", post(words::move_words))
+        .route("/words/{id}", get(words::word))
+        .route("/words/{id}/move", post(words::move_word))
+        .with_state(state)
 }
@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+use axum::extract::{FromRef, FromRequestParts};
+use axum::http::StatusCode;
+use axum::http::header::AUTHORIZATION;
+use axum::http::request::Parts;
+
+use crate::states::config::Config;
+
+/// Guards admin endpoints, which require `Authorization: Bearer <admin token>`.
+pub struct Admin;
+
+/// Guards endpoints that change the fridge, which require `Authorization: Bearer <API token>`.
+pub struct ApiClient;
+
+impl<S> FromRequestParts<S> for Admin
+where
+    Arc<Config>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, &'static str);
+
+    #[expect(
+        clippy::unused_async_trait_impl,
+        reason = "The trait requires an async fn"
+    )]
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let config = Arc::<Config>::from_ref(state);
+
+        let Some(admin_token) = config.admin_token.as_deref() else {
+            return Err((StatusCode::NOT_FOUND, "admin endpoints are disabled"));
+        };
+
+        if is_bearer_of(parts, admin_token) {
+            Ok(Admin)
+        } else {
+            Err((StatusCode::UNAUTHORIZED, "invalid admin token"))
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for ApiClient
+where
+    Arc<Config>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, &'static str);
+
+    #[expect(
+        clippy::unused_async_trait_impl,
+        reason = "The trait requires an async fn"
+    )]
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let config = Arc::<Config>::from_ref(state);
+
+        let Some(api_token) = config.api_token.as_deref() else {
+            return Err((
+                StatusCode::NOT_FOUND,
+                "moving words over the API is disabled",
+            ));
+        };
+
+        if is_bearer_of(parts, api_token) {
+            Ok(ApiClient)
+        } else {
+            Err((StatusCode::UNAUTHORIZED, "invalid API token"))
+        }
+    }
+}
+
+/// Whether the request carries `Authorization: Bearer <token>`.
+fn is_bearer_of(parts: &Parts, token: &str) -> bool {
+    parts
+        .headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|bearer| tokens_match(bearer, token))
+}
+
+/// Compares tokens in time independent of where they differ, so they can't be guessed byte by byte.
+fn tokens_match(token: &str, expected: &str) -> bool {
+    token.len() == expected.len()
+        && token
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::router::api_router::auth::tokens_match;
+
+    #[test]
+    fn matching_tokens() {
+        assert!(tokens_match("s3cret", "s3cret"), "same token");
+        assert!(!tokens_match("s3creT", "s3cret"), "different token");
+        assert!(!tokens_match("s3cre", "s3cret"), "prefix");
+        assert!(!tokens_match("", "s3cret"), "empty");
+    }
+}
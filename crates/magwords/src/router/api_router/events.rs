@@ -0,0 +1,77 @@
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use axum::Json;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse as _, Response};
+use serde::Deserialize;
+use tokio_stream::StreamExt as _;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{Level, event};
+
+use crate::router::api_router::fridge::{FridgeParams, find_fridge};
+use crate::words::MoveEventParams;
+use crate::words::connections::{Admission, ClientAddress};
+use crate::words::fridges::Fridges;
+use crate::words::sse;
+
+#[derive(Deserialize)]
+pub struct MoveParams {
+    /// Defaults to the default fridge.
+    fridge: Option<String>,
+    /// What the `poet` event at the start of [`events`] said.
+    poet: String,
+}
+
+/// The messages a poet on the websocket gets, for poets who can't use websockets.
+/// Each event carries the same JSON as a websocket message, after a `poet` event with the id to move words with.
+///
+/// Like websockets, streams count towards the connection limits, and fridges nobody is on aren't created.
+pub async fn events(
+    Admission {
+        address,
+        connection,
+    }: Admission,
+    State(fridges): State<Arc<Fridges>>,
+    Query(FridgeParams { fridge }): Query<FridgeParams>,
+) -> Response {
+    let connection = match connection {
+        Ok(connection) => connection,
+        Err(limit) => {
+            event!(Level::INFO, %address, ?limit, "Turning poet away");
+
+            return (StatusCode::SERVICE_UNAVAILABLE, limit.reason()).into_response();
+        },
+    };
+
+    let ws_state = match find_fridge(&fridges, fridge.as_deref()).await {
+        Ok(ws_state) => ws_state,
+        Err(error) => return error.into_response(),
+    };
+
+    let (poet, messages) = sse::subscribe(ws_state, connection);
+
+    let stream = tokio_stream::once(Event::default().event("poet").data(poet))
+        .chain(ReceiverStream::new(messages).map(|json| Event::default().data(json.as_str())))
+        .map(Ok::<_, Infallible>);
+
+    Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+/// Moves a word for a poet on [`events`], taking the same JSON as a `move` over the websocket,
+/// and counting against the same move limits.
+pub async fn submit_move(
+    ClientAddress(address): ClientAddress,
+    State(fridges): State<Arc<Fridges>>,
+    Query(MoveParams { fridge, poet }): Query<MoveParams>,
+    Json(move_event): Json<MoveEventParams>,
+) -> Response {
+    match find_fridge(&fridges, fridge.as_deref()).await {
+        Ok(ws_state) => sse::submit_move(&ws_state, &poet, address, move_event).await,
+        Err(error) => error.into_response(),
+    }
+}
@@ -0,0 +1,162 @@
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use axum::Json;
+use axum::body::Body;
+use axum::extract::{Query, State};
+use axum::http::header::CONTENT_TYPE;
+use axum::http::{HeaderValue, StatusCode};
+use axum::response::{IntoResponse as _, Response};
+use serde::Deserialize;
+use tracing::{Level, event};
+
+use crate::router::api_router::auth::Admin;
+use crate::words::fridges::{DEFAULT_FRIDGE, Fridges};
+use crate::words::history::HistoryEntry;
+use crate::words::replay::ReplayParams;
+use crate::words::{RevertReport, WsState};
+
+#[derive(Deserialize)]
+pub struct FridgeParams {
+    /// Defaults to the default fridge.
+    pub fridge: Option<String>,
+}
+
+/// Looks up a fridge someone is on, without creating it.
+///
+/// # Errors
+/// * Nobody is on the fridge
+pub async fn find_fridge(
+    fridges: &Fridges,
+    fridge: Option<&str>,
+) -> Result<Arc<WsState>, (StatusCode, &'static str)> {
+    fridges
+        .get(fridge.unwrap_or(DEFAULT_FRIDGE))
+        .await
+        .ok_or((StatusCode::NOT_FOUND, "no such fridge"))
+}
+
+/// Streams the moves made on a fridge between `from` and `to` as newline delimited JSON, oldest first.
+pub async fn replay(
+    State(fridges): State<Arc<Fridges>>,
+    Query(FridgeParams { fridge }): Query<FridgeParams>,
+    Query(replay_params): Query<ReplayParams>,
+) -> Response {
+    let replay = match replay_params.replay() {
+        Ok(replay) => replay,
+        Err(error) => return error.into_response(),
+    };
+
+    let ws_state = match find_fridge(&fridges, fridge.as_deref()).await {
+        Ok(ws_state) => ws_state,
+        Err(error) => return error.into_response(),
+    };
+
+    let mut response = ndjson(ws_state.history_between(replay.from, replay.to)).into_response();
+
+    response.headers_mut().insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("application/x-ndjson"),
+    );
+
+    response
+}
+
+/// `entries` as newline delimited JSON, serialized a line at a time as the body is sent.
+fn ndjson(entries: Vec<HistoryEntry>) -> Body {
+    let lines = entries
+        .into_iter()
+        .filter_map(|entry| match serde_json::to_string(&entry) {
+            Ok(mut line) => {
+                line.push('\n');
+
+                Some(Ok::<_, Infallible>(line))
+            },
+            Err(error) => {
+                event!(
+                    Level::ERROR,
+                    ?error,
+                    ?entry,
+                    "failed to serialize history entry, this is a bug"
+                );
+
+                None
+            },
+        });
+
+    Body::from_stream(tokio_stream::iter(lines))
+}
+
+#[derive(Deserialize)]
+pub struct RevertRequest {
+    /// Defaults to the default fridge.
+    fridge: Option<String>,
+    /// Milliseconds since the Unix epoch.
+    to: u64,
+}
+
+/// Puts a fridge back to how it was at a given time, as far as its history goes.
+pub async fn revert(
+    _admin: Admin,
+    State(fridges): State<Arc<Fridges>>,
+    Json(RevertRequest { fridge, to }): Json<RevertRequest>,
+) -> Result<Json<RevertReport>, (StatusCode, &'static str)> {
+    let ws_state = find_fridge(&fridges, fridge.as_deref()).await?;
+
+    let report = ws_state.revert_to(to).await;
+
+    event!(
+        Level::INFO,
+        fridge = ws_state.name(),
+        to,
+        ?report,
+        "Fridge reverted"
+    );
+
+    Ok(Json(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use tokio_stream::StreamExt as _;
+
+    use crate::router::api_router::fridge::ndjson;
+    use crate::words::history::{HistoryEntry, Position};
+
+    fn entry(id: usize, at: u64) -> HistoryEntry {
+        HistoryEntry {
+            id,
+            from: Position { x: 1, y: 2 },
+            to: Position { x: 3, y: 4 },
+            client_id: Some(7),
+            at,
+        }
+    }
+
+    #[tokio::test]
+    async fn streams_a_line_per_move() {
+        let mut body = ndjson(vec![entry(1, 100), entry(2, 200)]).into_data_stream();
+
+        let mut lines = Vec::new();
+
+        while let Some(chunk) = body.next().await {
+            lines.push(String::from_utf8(chunk.unwrap().to_vec()).unwrap());
+        }
+
+        assert_eq!(
+            lines,
+            [
+                concat!(
+                    r#"{"id":1,"from":{"x":1,"y":2},"to":{"x":3,"y":4},"client_id":7,"at":100}"#,
+                    "\n"
+                ),
+                concat!(
+                    r#"{"id":2,"from":{"x":1,"y":2},"to":{"x":3,"y":4},"client_id":7,"at":200}"#,
+                    "\n"
+                ),
+            ],
+            "one chunk per move, each a line of JSON"
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use axum::Json;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use crate::router::api_router::auth::ApiClient;
+use crate::router::api_router::fridge::{FridgeParams, find_fridge};
+use crate::states::config::FridgeDimensions;
+use crate::words::fridges::Fridges;
+use crate::words::history::Position;
+use crate::words::{MoveOutcome, WordInfo};
+
+#[derive(Serialize)]
+pub struct Poets {
+    count: usize,
+}
+
+/// Every word on the fridge, and where it is.
+pub async fn words(
+    State(fridges): State<Arc<Fridges>>,
+    Query(FridgeParams { fridge }): Query<FridgeParams>,
+) -> Result<Json<Vec<WordInfo>>, (StatusCode, &'static str)> {
+    let ws_state = find_fridge(&fridges, fridge.as_deref()).await?;
+
+    Ok(Json(ws_state.words().await))
+}
+
+pub async fn word(
+    State(fridges): State<Arc<Fridges>>,
+    Path(id): Path<usize>,
+    Query(FridgeParams { fridge }): Query<FridgeParams>,
+) -> Result<Json<WordInfo>, (StatusCode, &'static str)> {
+    let ws_state = find_fridge(&fridges, fridge.as_deref()).await?;
+
+    ws_state
+        .word(id)
+        .await
+        .map(Json)
+        .ok_or((StatusCode::NOT_FOUND, "no such word"))
+}
+
+pub async fn config(State(fridge_dimensions): State<FridgeDimensions>) -> Json<FridgeDimensions> {
+    Json(fridge_dimensions)
+}
+
+/// How many poets are on the fridge right now.
+pub async fn poets(
+    State(fridges): State<Arc<Fridges>>,
+    Query(FridgeParams { fridge }): Query<FridgeParams>,
+) -> Result<Json<Poets>, (StatusCode, &'static str)> {
+    let ws_state = find_fridge(&fridges, fridge.as_deref()).await?;
+
+    Ok(Json(Poets {
+        count: ws_state.poets(),
+    }))
+}
+
+/// Most moves in a single batch.
+const MAX_BATCH_SIZE: usize = 1000;
+
+#[derive(Deserialize)]
+pub struct MoveRequest {
+    /// The version of the word the move is made against. When left out, the move is made
+    /// against whatever version the word is at.
+    v: Option<usize>,
+    x: u32,
+    y: u32,
+}
+
+#[derive(Deserialize)]
+pub struct BatchMoveRequest {
+    id: usize,
+    v: Option<usize>,
+    x: u32,
+    y: u32,
+}
+
+/// Moves a word, like a poet dropping it on the fridge would.
+pub async fn move_word(
+    _api_client: ApiClient,
+    State(fridges): State<Arc<Fridges>>,
+    Path(id): Path<usize>,
+    Query(FridgeParams { fridge }): Query<FridgeParams>,
+    Json(MoveRequest { v, x, y }): Json<MoveRequest>,
+) -> Result<(StatusCode, Json<MoveOutcome>), (StatusCode, &'static str)> {
+    let ws_state = find_fridge(&fridges, fridge.as_deref()).await?;
+
+    let outcome = ws_state.move_from_api(id, v, Position { x, y }).await;
+
+    let status_code = match outcome {
+        MoveOutcome::Moved(_) => StatusCode::OK,
+        MoveOutcome::Stale(_) | MoveOutcome::Grabbed(_) => StatusCode::CONFLICT,
+        MoveOutcome::OutOfBounds => StatusCode::BAD_REQUEST,
+        MoveOutcome::UnknownWord => StatusCode::NOT_FOUND,
+    };
+
+    Ok((status_code, Json(outcome)))
+}
+
+/// Moves words in order, reporting what became of each move.
+/// A move that is refused doesn't stop the ones after it.
+pub async fn move_words(
+    _api_client: ApiClient,
+    State(fridges): State<Arc<Fridges>>,
+    Query(FridgeParams { fridge }): Query<FridgeParams>,
+    Json(moves): Json<Vec<BatchMoveRequest>>,
+) -> Result<Json<Vec<MoveOutcome>>, (StatusCode, &'static str)> {
+    if moves.len() > MAX_BATCH_SIZE {
+        return Err((StatusCode::BAD_REQUEST, "at most 1000 moves per batch"));
+    }
+
+    let ws_state = find_fridge(&fridges, fridge.as_deref()).await?;
+
+    let mut outcomes = Vec::with_capacity(moves.len());
+
+    for BatchMoveRequest { id, v, x, y } in moves {
+        outcomes.push(ws_state.move_from_api(id, v, Position { x, y }).await);
+    }
+
+    Ok(Json(outcomes))
+}
@@ -1,33 +1,240 @@
+pub mod redirect;
+#[cfg(unix)]
+pub mod systemd;
+pub mod tls;
+#[cfg(unix)]
+pub mod unix;
+
 use std::net::SocketAddr;
+use std::sync::Arc;
 
 use axum::Router;
+use axum::serve::{Listener, ListenerExt as _};
 use color_eyre::eyre::{self, Context as _};
+use socket2::{Domain, Socket, Type};
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tokio::task::JoinSet;
+use tokio_rustls::TlsAcceptor;
 use tokio_util::sync::CancellationToken;
 use tracing::{Level, event};
 
-/// Set up server on socket, with a router, and a cancellation token for graceful shutdown.
+use crate::server::redirect::build_redirect_router;
+use crate::server::tls::{Certificates, TlsListener};
+#[cfg(unix)]
+use crate::server::unix::LocalListener;
+use crate::states::config::{BindAddress, TlsConfig};
+
+const BACKLOG: i32 = 1024;
+
+type Servers = JoinSet<Result<(), eyre::Report>>;
+
+/// A listener, bound by us or by systemd.
+pub enum Bound {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+/// Set up server on each socket, with a router, and a cancellation token for graceful shutdown.
+/// With `tls`, the sockets speak HTTPS, and plain HTTP is redirected to them.
+///
+/// All addresses are bound before any is served, and when one listener fails, the others shut down too.
 ///
 /// # Errors
-/// * Couldn't bind to address
+/// * Couldn't bind to an address
+/// * There's nothing to listen on
+/// * Couldn't load the certificate or key
 /// * Server failure
 pub async fn setup_server(
-    bind_to: SocketAddr,
+    bind_to: &[BindAddress],
+    tls: Option<&TlsConfig>,
     router: Router,
     token: CancellationToken,
 ) -> Result<(), eyre::Report> {
-    event!(Level::INFO, ?bind_to, "Trying to bind");
+    let listeners = bind_all(bind_to).await?;
+
+    // or we'd be done serving right away
+    if listeners.is_empty() {
+        return Err(eyre::Report::msg(
+            "Nothing to listen on, `--bind` some addresses",
+        ));
+    }
+
+    let mut servers = JoinSet::new();
+
+    if let Some(tls) = tls {
+        let redirects = bind_all(&tls.redirect_from).await?;
+
+        let certificates = Arc::new(Certificates::load(tls).await?);
+
+        let acceptor = TlsAcceptor::from(Arc::new(certificates.server_config()?));
+
+        for listener in listeners {
+            serve(&mut servers, listener, Some(&acceptor), &router, &token)?;
+        }
+
+        // the port the outside world uses may well be another one, when behind NAT, but it's our best guess
+        let https_port = bind_to.iter().find_map(BindAddress::port).unwrap_or(443);
+
+        let redirect_router = build_redirect_router(https_port);
+
+        for listener in redirects {
+            serve(&mut servers, listener, None, &redirect_router, &token)?;
+        }
+
+        let token = token.clone();
+
+        servers.spawn(async move {
+            certificates.reload_on_change(token).await;
+
+            Ok(())
+        });
+    } else {
+        for listener in listeners {
+            serve(&mut servers, listener, None, &router, &token)?;
+        }
+    }
+
+    let mut result = Ok(());
+
+    while let Some(server) = servers.join_next().await {
+        let server = server.map_err(eyre::Report::from).and_then(|served| served);
+
+        if let Err(error) = server {
+            token.cancel();
+
+            if result.is_ok() {
+                result = Err(error);
+            }
+        }
+    }
+
+    result
+}
+
+fn serve(
+    servers: &mut Servers,
+    listener: Bound,
+    acceptor: Option<&TlsAcceptor>,
+    router: &Router,
+    token: &CancellationToken,
+) -> Result<(), eyre::Report> {
+    match listener {
+        Bound::Tcp(listener) => {
+            let listener = listener.tap_io(|stream| {
+                if let Err(error) = stream.set_nodelay(true) {
+                    event!(Level::TRACE, ?error, "Failed to set TCP_NODELAY");
+                }
+            });
+
+            serve_tls_or_plain(servers, listener, acceptor, router, token)
+        },
+        #[cfg(unix)]
+        Bound::Unix(listener) => {
+            serve_tls_or_plain(servers, LocalListener(listener), acceptor, router, token)
+        },
+    }
+}
+
+fn serve_tls_or_plain<L>(
+    servers: &mut Servers,
+    listener: L,
+    acceptor: Option<&TlsAcceptor>,
+    router: &Router,
+    token: &CancellationToken,
+) -> Result<(), eyre::Report>
+where
+    L: Listener<Addr = SocketAddr>,
+{
+    match acceptor {
+        Some(acceptor) => {
+            let listener = TlsListener::new(listener, acceptor.clone())
+                .wrap_err("Failed to set up TLS listener")?;
 
-    let listener = tokio::net::TcpListener::bind(bind_to)
-        .await
-        .wrap_err("Failed to bind Webserver to port")?;
+            spawn_server(servers, listener, router.clone(), token);
+        },
+        None => {
+            spawn_server(servers, listener, router.clone(), token);
+        },
+    }
 
-    event!(Level::INFO, ?bind_to, "Webserver bound successfully");
+    Ok(())
+}
+
+fn spawn_server<L>(servers: &mut Servers, listener: L, router: Router, token: &CancellationToken)
+where
+    L: Listener<Addr = SocketAddr>,
+{
+    // tapping gets us `ConnectInfo<SocketAddr>`, which axum only has for a plain `TcpListener` otherwise
+    let listener = listener.tap_io(|_| {});
 
-    axum::serve(
+    let server = axum::serve(
         listener,
-        router.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+        router.into_make_service_with_connect_info::<SocketAddr>(),
     )
-    .with_graceful_shutdown(token.cancelled_owned())
-    .await
-    .map_err(Into::into)
+    .with_graceful_shutdown(token.clone().cancelled_owned());
+
+    servers.spawn(async move { server.await.map_err(Into::into) });
+}
+
+async fn bind_all(addresses: &[BindAddress]) -> Result<Vec<Bound>, eyre::Report> {
+    let mut listeners = Vec::with_capacity(addresses.len());
+
+    for address in addresses {
+        match *address {
+            BindAddress::Tcp(address) => {
+                event!(Level::INFO, bind_to = ?address, "Trying to bind");
+
+                let listener = bind(address)
+                    .wrap_err_with(|| format!("Failed to bind Webserver to {}", address))?;
+
+                event!(Level::INFO, bind_to = ?address, "Webserver bound successfully");
+
+                listeners.push(Bound::Tcp(listener));
+            },
+            #[cfg(unix)]
+            BindAddress::Unix(ref path) => {
+                event!(Level::INFO, bind_to = %path.display(), "Trying to bind");
+
+                let listener = unix::bind(path)
+                    .await
+                    .wrap_err_with(|| format!("Failed to bind Webserver to {}", path.display()))?;
+
+                event!(Level::INFO, bind_to = %path.display(), "Webserver bound successfully");
+
+                listeners.push(Bound::Unix(listener));
+            },
+            #[cfg(unix)]
+            BindAddress::Systemd => {
+                listeners.extend(systemd::listeners()?);
+            },
+            #[cfg(not(unix))]
+            BindAddress::Unix(_) | BindAddress::Systemd => {
+                return Err(eyre::Report::msg(format!(
+                    "Can't listen on {}, which takes Unix",
+                    address
+                )));
+            },
+        }
+    }
+
+    Ok(listeners)
+}
+
+/// Binds a listener to `address`. IPv6 listeners only accept IPv6, so `0.0.0.0` and `[::]` can be bound side by side.
+fn bind(address: SocketAddr) -> Result<TcpListener, std::io::Error> {
+    let socket = Socket::new(Domain::for_address(address), Type::STREAM, None)?;
+
+    if address.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&address.into())?;
+    socket.listen(BACKLOG)?;
+
+    TcpListener::from_std(socket.into())
 }
@@ -0,0 +1,76 @@
+use axum::Router;
+use axum::http::header::HOST;
+use axum::http::uri::{Authority, PathAndQuery};
+use axum::http::{HeaderMap, StatusCode, Uri};
+use axum::response::{IntoResponse as _, Redirect, Response};
+
+/// Sends every request to the same place over HTTPS, on `https_port`.
+pub fn build_redirect_router(https_port: u16) -> Router {
+    Router::new().fallback(move |headers: HeaderMap, uri: Uri| async move {
+        match location(https_port, &headers, &uri) {
+            Some(location) => Redirect::permanent(&location).into_response(),
+            None => redirect_without_host(),
+        }
+    })
+}
+
+fn redirect_without_host() -> Response {
+    (StatusCode::BAD_REQUEST, "Use HTTPS").into_response()
+}
+
+/// Where the request should have gone, `None` when we can't tell which host it was for.
+fn location(https_port: u16, headers: &HeaderMap, uri: &Uri) -> Option<String> {
+    let authority = headers
+        .get(HOST)
+        .and_then(|host| host.to_str().ok())
+        .and_then(|host| host.parse::<Authority>().ok())?;
+
+    let host = authority.host();
+
+    let path_and_query = uri.path_and_query().map_or("/", PathAndQuery::as_str);
+
+    Some(if https_port == 443 {
+        format!("https://{}{}", host, path_and_query)
+    } else {
+        format!("https://{}:{}{}", host, https_port, path_and_query)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{HeaderMap, HeaderValue, Uri};
+    use pretty_assertions::assert_eq;
+
+    use crate::server::redirect::location;
+
+    fn host(host: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+
+        headers.insert("host", HeaderValue::from_static(host));
+
+        headers
+    }
+
+    #[test]
+    fn redirects_to_the_https_port() {
+        let uri = Uri::from_static("/ws/kitchen?replay=true");
+
+        assert_eq!(
+            location(443, &host("example.com:80"), &uri).as_deref(),
+            Some("https://example.com/ws/kitchen?replay=true")
+        );
+        assert_eq!(
+            location(3443, &host("example.com"), &uri).as_deref(),
+            Some("https://example.com:3443/ws/kitchen?replay=true")
+        );
+        assert_eq!(
+            location(3443, &host("[2001:db8::1]:3000"), &Uri::from_static("/")).as_deref(),
+            Some("https://[2001:db8::1]:3443/")
+        );
+        assert_eq!(
+            location(443, &HeaderMap::new(), &uri),
+            None,
+            "no host to redirect to"
+        );
+    }
+}
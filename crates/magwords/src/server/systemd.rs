@@ -0,0 +1,116 @@
+use std::env;
+use std::os::fd::{FromRawFd as _, OwnedFd, RawFd};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use color_eyre::eyre::{self, Context as _};
+use socket2::{Socket, Type};
+use tokio::net::{TcpListener, UnixListener};
+use tracing::{Level, event};
+
+use crate::server::Bound;
+
+/// The first file descriptor systemd passes, after stdin, stdout and stderr.
+const SD_LISTEN_FDS_START: RawFd = 3;
+
+/// The variables systemd passes the listeners with.
+const VARIABLES: [&str; 3] = ["LISTEN_PID", "LISTEN_FDS", "LISTEN_FDNAMES"];
+
+/// Whether we took ownership of the file descriptors already, which we may only do once.
+static TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// `LISTEN_PID` and `LISTEN_FDS`, as systemd set them, see [`take_environment`].
+static ENVIRONMENT: OnceLock<(Option<String>, Option<String>)> = OnceLock::new();
+
+/// Takes the variables systemd passes the listeners with out of the environment, for [`listeners`],
+/// like `sd_listen_fds(3)` does when asked to, so that no process we start thinks they're for it.
+///
+/// # Safety
+/// No other thread may be running, which could be reading the environment meanwhile.
+pub unsafe fn take_environment() {
+    ENVIRONMENT.get_or_init(|| (env::var("LISTEN_PID").ok(), env::var("LISTEN_FDS").ok()));
+
+    for variable in VARIABLES {
+        // SAFETY: the caller makes sure no other thread is running
+        unsafe {
+            env::remove_var(variable);
+        }
+    }
+}
+
+/// The listeners systemd opened for us, passed as file descriptors from 3 onwards, see `sd_listen_fds(3)`.
+///
+/// # Errors
+/// * systemd didn't pass any, passed them to another process, or we took them already
+/// * One of them isn't a TCP or Unix stream socket
+pub fn listeners() -> Result<Vec<Bound>, eyre::Report> {
+    let (pid, count) = ENVIRONMENT
+        .get()
+        .map_or((None, None), |&(ref pid, ref count)| {
+            (pid.as_deref(), count.as_deref())
+        });
+
+    let pid =
+        pid.ok_or_else(|| eyre::Report::msg("LISTEN_PID isn't set, are we socket activated?"))?;
+
+    if pid.parse::<u32>().ok() != Some(std::process::id()) {
+        return Err(eyre::Report::msg(
+            "The listeners from systemd were meant for another process",
+        ));
+    }
+
+    let count = count
+        .ok_or_else(|| eyre::Report::msg("LISTEN_FDS isn't set"))?
+        .parse::<RawFd>()
+        .wrap_err("LISTEN_FDS isn't a number")?;
+
+    // or we'd have nothing to listen on
+    if count < 1 {
+        return Err(eyre::Report::msg("systemd didn't pass any listeners"));
+    }
+
+    if TAKEN.swap(true, Ordering::Relaxed) {
+        return Err(eyre::Report::msg(
+            "The listeners from systemd can be used once, list `systemd` only once",
+        ));
+    }
+
+    (SD_LISTEN_FDS_START..SD_LISTEN_FDS_START.saturating_add(count))
+        .map(listener)
+        .collect()
+}
+
+fn listener(fd: RawFd) -> Result<Bound, eyre::Report> {
+    // SAFETY: systemd passed the file descriptor on for us to own, and we only take it once
+    let socket = Socket::from(unsafe { OwnedFd::from_raw_fd(fd) });
+
+    if socket.r#type().wrap_err("Not a socket")? != Type::STREAM {
+        return Err(eyre::Report::msg(format!(
+            "File descriptor {} isn't a stream socket",
+            fd
+        )));
+    }
+
+    socket.set_nonblocking(true)?;
+
+    let address = socket.local_addr()?;
+
+    if let Some(address) = address.as_socket() {
+        event!(Level::INFO, bind_to = ?address, fd, "Listening on socket from systemd");
+
+        return Ok(Bound::Tcp(TcpListener::from_std(socket.into())?));
+    }
+
+    if address.is_unix() {
+        event!(Level::INFO, bind_to = ?address.as_pathname(), fd, "Listening on socket from systemd");
+
+        let listener = std::os::unix::net::UnixListener::from(OwnedFd::from(socket));
+
+        return Ok(Bound::Unix(UnixListener::from_std(listener)?));
+    }
+
+    Err(eyre::Report::msg(format!(
+        "File descriptor {} isn't a TCP or Unix socket",
+        fd
+    )))
+}
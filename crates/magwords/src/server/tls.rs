@@ -0,0 +1,258 @@
+use std::fmt::Debug;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::{Duration, SystemTime};
+
+use axum::serve::Listener;
+use color_eyre::eyre::{self, Context as _};
+use rustls::ServerConfig;
+use rustls::crypto::CryptoProvider;
+use rustls::crypto::ring::default_provider;
+use rustls::pki_types::pem::PemObject as _;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::server::TlsStream;
+use tokio_util::sync::CancellationToken;
+use tracing::{Level, event};
+
+use crate::states::config::TlsConfig;
+
+/// How often the certificate and key are checked for changes.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long a client gets to complete the TLS handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Handshaken connections waiting to be served.
+const ACCEPTED_CAPACITY: usize = 64;
+
+/// The certificate we present, swapped for a new one when its files change.
+#[derive(Debug)]
+pub struct Certificates {
+    certificate: PathBuf,
+    key: PathBuf,
+    provider: Arc<CryptoProvider>,
+    current: RwLock<Arc<CertifiedKey>>,
+}
+
+impl Certificates {
+    /// # Errors
+    /// * The certificate or key can't be read, don't parse, or don't match
+    pub async fn load(tls: &TlsConfig) -> Result<Self, eyre::Report> {
+        let provider = Arc::new(default_provider());
+
+        let current = load(&tls.certificate, &tls.key, &provider).await?;
+
+        Ok(Self {
+            certificate: tls.certificate.clone(),
+            key: tls.key.clone(),
+            provider,
+            current: RwLock::new(Arc::new(current)),
+        })
+    }
+
+    /// # Errors
+    /// * The crypto provider doesn't support the default protocol versions, which is a bug
+    pub fn server_config(self: &Arc<Self>) -> Result<ServerConfig, eyre::Report> {
+        let resolver: Arc<dyn ResolvesServerCert> = Arc::<Self>::clone(self);
+
+        let mut config = ServerConfig::builder_with_provider(Arc::clone(&self.provider))
+            .with_safe_default_protocol_versions()
+            .wrap_err("Failed to set up TLS")?
+            .with_no_client_auth()
+            .with_cert_resolver(resolver);
+
+        // websockets need HTTP/1.1, which is all we serve
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+        Ok(config)
+    }
+
+    /// Reloads the certificate and key when either file changes, until cancelled.
+    /// When the new ones can't be loaded, say because only one of them was replaced yet, we keep the old ones.
+    pub async fn reload_on_change(self: Arc<Self>, token: CancellationToken) {
+        let mut modified = self.modified().await;
+
+        let mut interval = tokio::time::interval(RELOAD_INTERVAL);
+
+        // the first tick completes immediately
+        interval.tick().await;
+
+        loop {
+            tokio::select! {
+                () = token.cancelled() => {
+                    break;
+                },
+                _ = interval.tick() => {},
+            }
+
+            let now_modified = self.modified().await;
+
+            if now_modified == modified {
+                continue;
+            }
+
+            match load(&self.certificate, &self.key, &self.provider).await {
+                Ok(certified_key) => {
+                    *self.current.write().unwrap_or_else(PoisonError::into_inner) =
+                        Arc::new(certified_key);
+
+                    modified = now_modified;
+
+                    event!(Level::INFO, certificate = %self.certificate.display(), "Reloaded TLS certificate");
+                },
+                Err(error) => {
+                    event!(Level::WARN, ?error, certificate = %self.certificate.display(), "Failed to reload TLS certificate, keeping the current one");
+                },
+            }
+        }
+    }
+
+    async fn modified(&self) -> Option<(SystemTime, SystemTime)> {
+        let modified = async |path: &Path| tokio::fs::metadata(path).await.ok()?.modified().ok();
+
+        Some((
+            modified(&self.certificate).await?,
+            modified(&self.key).await?,
+        ))
+    }
+}
+
+impl ResolvesServerCert for Certificates {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(Arc::clone(
+            &self.current.read().unwrap_or_else(PoisonError::into_inner),
+        ))
+    }
+}
+
+async fn load(
+    certificate: &Path,
+    key: &Path,
+    provider: &CryptoProvider,
+) -> Result<CertifiedKey, eyre::Report> {
+    let certificate_pem = tokio::fs::read(certificate)
+        .await
+        .wrap_err_with(|| format!("Failed to read {}", certificate.display()))?;
+
+    let key_pem = tokio::fs::read(key)
+        .await
+        .wrap_err_with(|| format!("Failed to read {}", key.display()))?;
+
+    let chain = CertificateDer::pem_slice_iter(&certificate_pem)
+        .collect::<Result<Vec<_>, _>>()
+        .wrap_err_with(|| format!("Failed to parse {}", certificate.display()))?;
+
+    if chain.is_empty() {
+        return Err(eyre::Report::msg(format!(
+            "No certificates in {}",
+            certificate.display()
+        )));
+    }
+
+    let key = PrivateKeyDer::from_pem_slice(&key_pem)
+        .wrap_err_with(|| format!("Failed to parse {}", key.display()))?;
+
+    let signing_key = provider
+        .key_provider
+        .load_private_key(key)
+        .wrap_err("Unsupported private key")?;
+
+    let certified_key = CertifiedKey::new(chain, signing_key);
+
+    certified_key
+        .keys_match()
+        .wrap_err("The private key doesn't belong to the certificate")?;
+
+    Ok(certified_key)
+}
+
+/// Accepts TLS connections on `L`, handshaking in the background so that slow clients don't hold up others.
+pub struct TlsListener<L: Listener> {
+    local_addr: L::Addr,
+    accepted: mpsc::Receiver<(TlsStream<L::Io>, L::Addr)>,
+}
+
+impl<L> TlsListener<L>
+where
+    L: Listener,
+    L::Addr: Clone + Debug + Sync + 'static,
+{
+    /// # Errors
+    /// * `listener` doesn't know its address
+    pub fn new(listener: L, acceptor: TlsAcceptor) -> Result<Self, io::Error> {
+        let local_addr = listener.local_addr()?;
+
+        let (accepted_tx, accepted) = mpsc::channel(ACCEPTED_CAPACITY);
+
+        // ends once we're dropped
+        tokio::spawn(accept(listener, acceptor, accepted_tx));
+
+        Ok(Self {
+            local_addr,
+            accepted,
+        })
+    }
+}
+
+impl<L> Listener for TlsListener<L>
+where
+    L: Listener,
+    L::Addr: Clone + Debug + Sync + 'static,
+{
+    type Io = TlsStream<L::Io>;
+    type Addr = L::Addr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.accepted.recv().await {
+            Some(accepted) => accepted,
+            // the accept loop only ends when we're gone, so this doesn't happen
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.local_addr.clone())
+    }
+}
+
+async fn accept<L>(
+    mut listener: L,
+    acceptor: TlsAcceptor,
+    accepted: mpsc::Sender<(TlsStream<L::Io>, L::Addr)>,
+) where
+    L: Listener,
+    L::Addr: Debug + 'static,
+{
+    loop {
+        let (stream, address) = tokio::select! {
+            () = accepted.closed() => {
+                break;
+            },
+            connection = listener.accept() => connection,
+        };
+
+        let acceptor = acceptor.clone();
+        let accepted = accepted.clone();
+
+        tokio::spawn(async move {
+            match timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                Ok(Ok(stream)) => {
+                    // when it fails we're shutting down
+                    let _r = accepted.send((stream, address)).await;
+                },
+                Ok(Err(error)) => {
+                    event!(Level::DEBUG, ?error, ?address, "TLS handshake failed");
+                },
+                Err(_) => {
+                    event!(Level::DEBUG, ?address, "TLS handshake timed out");
+                },
+            }
+        });
+    }
+}
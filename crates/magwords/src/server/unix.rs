@@ -0,0 +1,51 @@
+use std::io;
+use std::net::SocketAddr;
+use std::os::unix::fs::FileTypeExt as _;
+use std::path::Path;
+
+use axum::serve::Listener;
+use tokio::net::{UnixListener, UnixStream};
+
+use crate::utils::forwarded::UNIX_PEER;
+
+/// Binds a Unix socket at `path`, replacing the one a previous run left behind.
+///
+/// # Errors
+/// * Something else is in the way, or still listening there
+pub async fn bind(path: &Path) -> Result<UnixListener, io::Error> {
+    let is_socket = tokio::fs::symlink_metadata(path)
+        .await
+        .is_ok_and(|metadata| metadata.file_type().is_socket());
+
+    if is_socket {
+        if UnixStream::connect(path).await.is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                "another process is listening on the socket",
+            ));
+        }
+
+        tokio::fs::remove_file(path).await?;
+    }
+
+    UnixListener::bind(path)
+}
+
+/// A Unix socket listener whose connections appear to come from [`UNIX_PEER`],
+/// a proxy on this machine that we trust.
+pub struct LocalListener(pub UnixListener);
+
+impl Listener for LocalListener {
+    type Io = UnixStream;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        let (stream, _address) = Listener::accept(&mut self.0).await;
+
+        (stream, UNIX_PEER)
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(UNIX_PEER)
+    }
+}
@@ -1,5 +1,5 @@
 #[cfg(not(target_os = "windows"))]
-use tokio::signal::unix::SignalKind;
+use tokio::signal::unix::{Signal, SignalKind};
 
 macro_rules! await_linux_only_signal {
     ($signal:expr) => {{
@@ -27,3 +27,30 @@ pub async fn wait_for_sigint() -> Result<(), std::io::Error> {
 
     Ok(())
 }
+
+/// The `SIGHUP`s we get, which never come on Windows.
+pub struct Hangups {
+    #[cfg(not(target_os = "windows"))]
+    signal: Signal,
+}
+
+impl Hangups {
+    /// Starts listening, from here on a `SIGHUP` no longer terminates us.
+    pub fn new() -> Result<Self, std::io::Error> {
+        Ok(Self {
+            #[cfg(not(target_os = "windows"))]
+            signal: tokio::signal::unix::signal(SignalKind::hangup())?,
+        })
+    }
+
+    /// Waits for the next `SIGHUP`.
+    pub async fn recv(&mut self) {
+        #[cfg(not(target_os = "windows"))]
+        if self.signal.recv().await.is_some() {
+            return;
+        }
+
+        // tokio never ends the stream
+        std::future::pending::<()>().await;
+    }
+}
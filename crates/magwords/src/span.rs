@@ -1,12 +1,21 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::extract::connect_info::ConnectInfo;
 use http::Request;
+use ipnet::IpNet;
 use tower_http::trace::MakeSpan;
+use tracing::field::{Empty, display};
 use tracing::{Level, Span};
 use uuid::Uuid;
 
+use crate::utils::forwarded::client_address;
+
 // Copied from https://github.com/tower-rs/tower-http/blob/35740decc663f4921b85b234ae33580f40fcbb31/tower-http/src/trace/mod.rs#L472
 const DEFAULT_MESSAGE_LEVEL: Level = Level::DEBUG;
 
-/// Creates a [`Span`] like [`DefaultMakeSpan`], with an added `id` so that individual requests can be traced all the way.
+/// Creates a [`Span`] like [`DefaultMakeSpan`], with an added `id` so that individual requests can be traced all the way,
+/// and the `client` who made them.
 ///
 /// [`Span`]: tracing::Span
 /// [`DefaultMakeSpan`]: tower_http::trace::DefaultMakeSpan
@@ -14,6 +23,7 @@ const DEFAULT_MESSAGE_LEVEL: Level = Level::DEBUG;
 pub struct MakeSpanWithUuid {
     level: Level,
     include_headers: bool,
+    trusted_proxies: Arc<[IpNet]>,
 }
 
 impl MakeSpanWithUuid {
@@ -22,6 +32,7 @@ impl MakeSpanWithUuid {
         Self {
             level: DEFAULT_MESSAGE_LEVEL,
             include_headers: false,
+            trusted_proxies: Arc::from([]),
         }
     }
 
@@ -45,6 +56,14 @@ impl MakeSpanWithUuid {
         self.include_headers = include_headers;
         self
     }
+
+    /// Set the proxies whose headers tell us the `client`, rather than the proxy, made the request.
+    ///
+    /// By default no proxy is trusted.
+    pub fn trusted_proxies(mut self, trusted_proxies: &[IpNet]) -> Self {
+        self.trusted_proxies = Arc::from(trusted_proxies);
+        self
+    }
 }
 
 impl Default for MakeSpanWithUuid {
@@ -69,6 +88,7 @@ impl<B> MakeSpan<B> for MakeSpanWithUuid {
                         uri = %request.uri(),
                         version = ?request.version(),
                         headers = ?request.headers(),
+                        client = Empty,
                     )
                 } else {
                     tracing::span!(
@@ -78,17 +98,32 @@ impl<B> MakeSpan<B> for MakeSpanWithUuid {
                         method = %request.method(),
                         uri = %request.uri(),
                         version = ?request.version(),
+                        client = Empty,
                     )
                 }
             }
         }
 
-        match self.level {
+        let span = match self.level {
             Level::ERROR => make_span!(Level::ERROR),
             Level::WARN => make_span!(Level::WARN),
             Level::INFO => make_span!(Level::INFO),
             Level::DEBUG => make_span!(Level::DEBUG),
             Level::TRACE => make_span!(Level::TRACE),
+        };
+
+        // absent when served without `into_make_service_with_connect_info`
+        if let Some(&ConnectInfo(peer)) = request.extensions().get::<ConnectInfo<SocketAddr>>() {
+            span.record(
+                "client",
+                display(client_address(
+                    peer,
+                    request.headers(),
+                    &self.trusted_proxies,
+                )),
+            );
         }
+
+        span
     }
 }
@@ -4,7 +4,8 @@ use axum::extract::{FromRef, FromRequestParts};
 use axum::http::request::Parts;
 
 use crate::states::config::{Config, FridgeDimensions};
-use crate::words::WsState;
+use crate::words::connections::Connections;
+use crate::words::fridges::Fridges;
 
 /// This is to be able to do:
 /// ```no_run
@@ -20,29 +21,42 @@ impl FromRef<ApplicationState> for Arc<Config> {
     }
 }
 
-impl FromRef<ApplicationState> for Arc<WsState> {
+impl FromRef<ApplicationState> for Arc<Fridges> {
     fn from_ref(input: &ApplicationState) -> Self {
-        Arc::clone(&input.ws_state)
+        Arc::clone(&input.fridges)
+    }
+}
+
+impl FromRef<ApplicationState> for Arc<Connections> {
+    fn from_ref(input: &ApplicationState) -> Self {
+        Arc::clone(&input.connections)
     }
 }
 
 impl FromRef<ApplicationState> for FridgeDimensions {
     fn from_ref(input: &ApplicationState) -> Self {
-        input.config.fridge_dimensions
+        input.fridges.fridge_dimensions()
     }
 }
 
 #[derive(Clone)]
 pub struct ApplicationState {
     pub config: Arc<Config>,
-    pub ws_state: Arc<WsState>,
+    pub fridges: Arc<Fridges>,
+    pub connections: Arc<Connections>,
 }
 
 impl ApplicationState {
-    pub fn new(config: Config, ws_state: Arc<WsState>) -> Self {
+    pub fn new(config: Config, fridges: Arc<Fridges>) -> Self {
+        let connections = Arc::new(Connections::new(
+            config.max_poets,
+            config.max_connections_per_address,
+        ));
+
         ApplicationState {
             config: Arc::new(config),
-            ws_state,
+            fridges,
+            connections,
         }
     }
 }
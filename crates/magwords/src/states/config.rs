@@ -1,11 +1,132 @@
+use std::fmt::{self, Display, Formatter};
 use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
 
-#[derive(Copy, Clone)]
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize, Serializer};
+
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
 pub struct FridgeDimensions {
     pub fridge_width: u32,
     pub fridge_height: u32,
 }
+
+/// Where to listen.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BindAddress {
+    Tcp(SocketAddr),
+    /// Poets connecting over a Unix socket come from a proxy on this machine, which we trust to tell who they are.
+    Unix(PathBuf),
+    /// The listeners systemd opened for us, see `systemd.socket(5)`.
+    Systemd,
+}
+
+impl BindAddress {
+    pub fn port(&self) -> Option<u16> {
+        match *self {
+            BindAddress::Tcp(address) => Some(address.port()),
+            BindAddress::Unix(_) | BindAddress::Systemd => None,
+        }
+    }
+}
+
+/// As `--bind` takes it.
+impl Display for BindAddress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            BindAddress::Tcp(address) => write!(f, "{}", address),
+            BindAddress::Unix(ref path) => write!(f, "unix:{}", path.display()),
+            BindAddress::Systemd => write!(f, "systemd"),
+        }
+    }
+}
+
+impl Serialize for BindAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// How many of something may happen per second on average, and at once.
+#[derive(Copy, Clone, Debug)]
+pub struct RateLimit {
+    pub per_second: f64,
+    pub burst: u32,
+}
+
+/// How fast poets may move words.
+#[derive(Copy, Clone, Debug)]
+pub struct MoveLimits {
+    pub poet: RateLimit,
+    /// Shared by the poets at the same address, unlimited when unset.
+    pub address: Option<RateLimit>,
+    /// Moves a poet may have dropped in a minute, before they're disconnected.
+    pub max_dropped: u32,
+}
+
+/// HTTPS and WSS, instead of plain HTTP and WS.
+pub struct TlsConfig {
+    /// PEM certificate chain, reloaded when it changes.
+    pub certificate: PathBuf,
+    /// PEM private key, reloaded along with the certificate.
+    pub key: PathBuf,
+    /// Where plain HTTP is redirected to HTTPS.
+    pub redirect_from: Vec<BindAddress>,
+}
+
+pub struct SnapshotConfig {
+    pub directory: PathBuf,
+    pub interval: Duration,
+}
+
+/// The word packs on a specific fridge.
+#[derive(Clone, Debug)]
+pub struct FridgePacks {
+    pub fridge: String,
+    pub packs: Vec<String>,
+}
+
+/// As `--fridge-packs` takes it.
+impl Display for FridgePacks {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.fridge, self.packs.join("+"))
+    }
+}
+
+impl Serialize for FridgePacks {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
 pub struct Config {
-    pub bind_to: SocketAddr,
+    pub bind_to: Vec<BindAddress>,
+    pub tls: Option<TlsConfig>,
     pub fridge_dimensions: FridgeDimensions,
+    pub snapshot: Option<SnapshotConfig>,
+    pub fridge_idle_timeout: Duration,
+    /// Time between two heartbeats, which also expire abandoned grabs.
+    pub heartbeat_interval: Duration,
+    /// How long a poet may take to answer a heartbeat.
+    pub pong_timeout: Duration,
+    pub max_fridges: usize,
+    pub word_lists: Vec<PathBuf>,
+    /// Packs for fridges without their own selection in `fridge_packs`, all packs when empty.
+    pub packs: Vec<String>,
+    pub fridge_packs: Vec<FridgePacks>,
+    /// Moves remembered per fridge, for reverting it.
+    pub history_size: usize,
+    /// Messages buffered per fridge before poets who fall behind need a fresh copy of it.
+    pub broadcast_capacity: usize,
+    pub move_limits: MoveLimits,
+    /// Websockets open at once, across all fridges.
+    pub max_poets: usize,
+    pub max_connections_per_address: usize,
+    /// Proxies whose `Forwarded`, `X-Forwarded-For` and `X-Real-IP` headers we believe.
+    pub trusted_proxies: Vec<IpNet>,
+    /// Token admin endpoints require, admin endpoints are disabled when unset.
+    pub admin_token: Option<String>,
+    /// Token required to move words over the REST API, which is read-only when unset.
+    pub api_token: Option<String>,
 }
@@ -1 +1,313 @@
+use std::sync::Arc;
+use std::time::Duration;
 
+use color_eyre::eyre;
+use tokio::time::{MissedTickBehavior, interval};
+use tokio_util::sync::CancellationToken;
+use tracing::{Level, event};
+
+use crate::build_configs;
+use crate::cli::Cli;
+use crate::log_filter::{self, LogFilter};
+use crate::signal_handlers::Hangups;
+use crate::states::config::Config;
+use crate::words::connections::Connections;
+use crate::words::drags::DRAG_INTERVAL;
+use crate::words::fridges::{Fridges, Settings};
+use crate::words::word_list;
+use crate::words::{PROTOCOL_VERSION, ServerMessage};
+
+/// Sends a heartbeat to all poets every `period`, and a goodbye when we're shutting down.
+pub async fn heartbeat(fridges: Arc<Fridges>, period: Duration, token: CancellationToken) {
+    let mut interval = interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    while !token.is_cancelled() {
+        interval.tick().await;
+
+        for ws_state in fridges.all().await {
+            ws_state.expire_grabs();
+        }
+
+        fridges
+            .broadcast(&ServerMessage::Hup {
+                id: 1,
+                v: PROTOCOL_VERSION,
+            })
+            .await;
+    }
+
+    fridges.broadcast(&ServerMessage::Goodbye {}).await;
+}
+
+/// Sends out where the words being dragged are, at most every [`DRAG_INTERVAL`].
+pub async fn stream_drags(fridges: Arc<Fridges>, token: CancellationToken) {
+    let mut interval = interval(DRAG_INTERVAL);
+    // catching up on missed ticks would only send the same positions in a burst
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        tokio::select! {
+            () = token.cancelled() => break,
+            _ = interval.tick() => {},
+        }
+
+        for ws_state in fridges.all().await {
+            ws_state.flush_drags();
+        }
+    }
+}
+
+/// Snapshots the fridges every `period`, and once more when we're shutting down.
+pub async fn snapshot_fridges(fridges: Arc<Fridges>, period: Duration, token: CancellationToken) {
+    let mut interval = interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    // the first tick completes immediately, and there is nothing to save yet
+    interval.tick().await;
+
+    loop {
+        tokio::select! {
+            () = token.cancelled() => break,
+            _ = interval.tick() => {},
+        }
+
+        fridges.save_all().await;
+    }
+
+    // and once more on the way out, so that a graceful shutdown loses nothing
+    fridges.save_all().await;
+}
+
+/// Periodically drops fridges nobody is using anymore.
+pub async fn evict_idle_fridges(fridges: Arc<Fridges>, token: CancellationToken) {
+    let mut interval = interval((fridges.idle_timeout() / 4).max(Duration::from_secs(1)));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            () = token.cancelled() => break,
+            _ = interval.tick() => {},
+        }
+
+        fridges.evict_idle().await;
+    }
+}
+
+/// Reloads the configuration and the word lists on every `SIGHUP`, and applies what can change
+/// with poets on the fridges: the words, the fridge dimensions, the limits and the log filter.
+/// The rest, such as where we listen, needs a restart, see `config`.
+pub async fn reload_on_hangup(
+    fridges: Arc<Fridges>,
+    connections: Arc<Connections>,
+    log_filter: LogFilter,
+    config: Arc<Config>,
+    token: CancellationToken,
+) {
+    let mut hangups = match Hangups::new() {
+        Ok(hangups) => hangups,
+        Err(error) => {
+            event!(
+                Level::ERROR,
+                ?error,
+                "Failed to register SIGHUP handler, aborting"
+            );
+            return;
+        },
+    };
+
+    loop {
+        tokio::select! {
+            () = token.cancelled() => break,
+            () = hangups.recv() => {},
+        }
+
+        event!(Level::INFO, "SIGHUP received, reloading configuration");
+
+        match reload(&fridges, &connections, &log_filter, &config).await {
+            Ok(()) => {
+                event!(Level::INFO, "Configuration reloaded");
+            },
+            Err(error) => {
+                event!(
+                    Level::ERROR,
+                    ?error,
+                    "Failed to reload configuration, keeping the current one"
+                );
+            },
+        }
+    }
+}
+
+/// Checks everything before changing anything, so that a mistake doesn't leave us half reloaded.
+/// `current` is the configuration we started with, which is what the rest keeps using.
+async fn reload(
+    fridges: &Fridges,
+    connections: &Connections,
+    log_filter: &LogFilter,
+    current: &Config,
+) -> Result<(), eyre::Report> {
+    let args = Cli::reload()?;
+
+    let config = build_configs(&args)?;
+
+    let filter = log_filter::parse(args.log_filter.as_deref())?;
+
+    let word_packs = word_list::load_word_packs(&config.word_lists).await?;
+
+    let settings = Settings::new(&word_packs, &config)?;
+
+    // the only change that can fail, so it goes first
+    log_filter.set(filter)?;
+
+    fridges.reload(settings).await;
+
+    connections.set_limits(config.max_poets, config.max_connections_per_address);
+
+    warn_about_restart(current, &config);
+
+    Ok(())
+}
+
+/// Warns about the settings in `reloaded` that differ from `current`, but are only read once
+/// when we start.
+fn warn_about_restart(current: &Config, reloaded: &Config) {
+    for setting in restart_only_changes(current, reloaded) {
+        event!(
+            Level::WARN,
+            setting,
+            "Setting changed, which only applies after a restart"
+        );
+    }
+}
+
+/// The settings that differ between `current` and `reloaded`, of those a reload doesn't apply.
+fn restart_only_changes(current: &Config, reloaded: &Config) -> Vec<&'static str> {
+    [
+        ("bind", current.bind_to != reloaded.bind_to),
+        (
+            "tls-certificate",
+            current.tls.as_ref().map(|tls| &tls.certificate)
+                != reloaded.tls.as_ref().map(|tls| &tls.certificate),
+        ),
+        (
+            "tls-key",
+            current.tls.as_ref().map(|tls| &tls.key) != reloaded.tls.as_ref().map(|tls| &tls.key),
+        ),
+        (
+            "http-redirect-bind",
+            current.tls.as_ref().map(|tls| &tls.redirect_from)
+                != reloaded.tls.as_ref().map(|tls| &tls.redirect_from),
+        ),
+        (
+            "snapshot-dir",
+            current
+                .snapshot
+                .as_ref()
+                .map(|snapshot| &snapshot.directory)
+                != reloaded
+                    .snapshot
+                    .as_ref()
+                    .map(|snapshot| &snapshot.directory),
+        ),
+        (
+            "snapshot-interval",
+            current.snapshot.as_ref().map(|snapshot| snapshot.interval)
+                != reloaded.snapshot.as_ref().map(|snapshot| snapshot.interval),
+        ),
+        (
+            "fridge-idle-timeout",
+            current.fridge_idle_timeout != reloaded.fridge_idle_timeout,
+        ),
+        (
+            "heartbeat-interval",
+            current.heartbeat_interval != reloaded.heartbeat_interval,
+        ),
+        (
+            "pong-timeout",
+            current.pong_timeout != reloaded.pong_timeout,
+        ),
+        ("max-fridges", current.max_fridges != reloaded.max_fridges),
+        (
+            "history-size",
+            current.history_size != reloaded.history_size,
+        ),
+        (
+            "broadcast-capacity",
+            current.broadcast_capacity != reloaded.broadcast_capacity,
+        ),
+        (
+            "trusted-proxies",
+            current.trusted_proxies != reloaded.trusted_proxies,
+        ),
+        ("admin-token", current.admin_token != reloaded.admin_token),
+        ("api-token", current.api_token != reloaded.api_token),
+    ]
+    .into_iter()
+    .filter_map(|(setting, changed)| changed.then_some(setting))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser as _;
+    use pretty_assertions::assert_eq;
+
+    use crate::build_configs;
+    use crate::cli::Cli;
+    use crate::states::config::Config;
+    use crate::tasks::restart_only_changes;
+
+    fn config(args: &[&str]) -> Config {
+        let cli =
+            Cli::try_parse_from(std::iter::once("magwords").chain(args.iter().copied())).unwrap();
+
+        build_configs(&cli).unwrap()
+    }
+
+    #[test]
+    fn tells_what_needs_a_restart() {
+        let current = config(&["--snapshot-dir", "snapshots"]);
+
+        assert_eq!(
+            restart_only_changes(
+                &current,
+                &config(&[
+                    "--snapshot-dir",
+                    "snapshots",
+                    "--fridge-width",
+                    "500",
+                    "--max-poets",
+                    "5",
+                    "--move-burst",
+                    "3",
+                ])
+            ),
+            Vec::<&str>::new(),
+            "all reloaded"
+        );
+        assert_eq!(
+            restart_only_changes(
+                &current,
+                &config(&[
+                    "--bind",
+                    "127.0.0.1:4000",
+                    "--snapshot-interval",
+                    "5",
+                    "--history-size",
+                    "5",
+                    "--api-token",
+                    "s3cret",
+                ])
+            ),
+            [
+                "bind",
+                "snapshot-dir",
+                "snapshot-interval",
+                "history-size",
+                "api-token"
+            ],
+            "none reloaded"
+        );
+    }
+}
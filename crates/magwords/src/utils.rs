@@ -1,4 +1,5 @@
 pub mod env;
+pub mod forwarded;
 pub mod url;
 
 use color_eyre::eyre;
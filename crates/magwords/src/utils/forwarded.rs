@@ -0,0 +1,265 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use axum::http::HeaderMap;
+use axum::http::header::FORWARDED;
+use ipnet::IpNet;
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+const X_REAL_IP: &str = "x-real-ip";
+
+/// Where connections over a Unix socket appear to come from, `127.0.0.1` with a port no TCP
+/// connection has. Only a proxy on this machine connects there, so it's trusted to tell who the poet
+/// is like a `--trusted-proxies` one, or the poets it passes on would all share one address's limits.
+pub const UNIX_PEER: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+
+/// The address of the client behind `peer`, as far as the proxies in between can be trusted.
+/// The port is 0 when a proxy didn't tell. A `peer` on a Unix socket, see [`UNIX_PEER`], is trusted too.
+///
+/// Proxies append the address they got the request from, so we walk the `Forwarded` (or when absent,
+/// `X-Forwarded-For`, or `X-Real-IP`) addresses from the last one back, until we find one that isn't
+/// a trusted proxy.
+pub fn client_address(
+    peer: SocketAddr,
+    headers: &HeaderMap,
+    trusted_proxies: &[IpNet],
+) -> SocketAddr {
+    let is_trusted = |address: SocketAddr| {
+        trusted_proxies
+            .iter()
+            .any(|proxy| proxy.contains(&address.ip()))
+    };
+
+    let mut client = peer;
+
+    // only the peer itself, a hop a proxy didn't give a port for has port 0 too
+    if client != UNIX_PEER && !is_trusted(client) {
+        return client;
+    }
+
+    let mut hops = forwarded_for(headers);
+
+    if hops.is_empty() {
+        hops = x_forwarded_for(headers, X_FORWARDED_FOR);
+    }
+
+    if hops.is_empty() {
+        hops = x_forwarded_for(headers, X_REAL_IP);
+    }
+
+    for hop in hops.into_iter().rev() {
+        // an obfuscated or unknown hop, the last proxy we trust is as far as we get
+        let Some(hop) = hop else {
+            break;
+        };
+
+        client = hop;
+
+        if !is_trusted(client) {
+            break;
+        }
+    }
+
+    client
+}
+
+/// Parses a trusted proxy, as a network, like `10.0.0.0/8`, or a single address.
+///
+/// # Errors
+/// * `value` is neither
+pub fn parse_trusted_proxy(value: &str) -> Result<IpNet, String> {
+    value
+        .parse::<IpNet>()
+        .or_else(|_| value.parse::<IpAddr>().map(IpNet::from))
+        .map_err(|_| {
+            format!(
+                "expected a network like `10.0.0.0/8`, or an address, got `{}`",
+                value
+            )
+        })
+}
+
+/// The `for=` of each element of the `Forwarded` headers, as described in RFC 7239.
+fn forwarded_for(headers: &HeaderMap) -> Vec<Option<SocketAddr>> {
+    headers
+        .get_all(FORWARDED)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|element| {
+            element.split(';').find_map(|pair| {
+                let (name, value) = pair.trim().split_once('=')?;
+
+                name.eq_ignore_ascii_case("for")
+                    .then(|| parse_node(value.trim_matches('"')))
+            })
+        })
+        .collect()
+}
+
+/// The addresses in `X-Forwarded-For`, or the one in `X-Real-IP`, which look alike.
+fn x_forwarded_for(headers: &HeaderMap, name: &str) -> Vec<Option<SocketAddr>> {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|node| parse_node(node.trim()))
+        .collect()
+}
+
+/// Parses `192.0.2.43`, `192.0.2.43:47011`, `[2001:db8::17]` and `[2001:db8::17]:47011` alike.
+fn parse_node(node: &str) -> Option<SocketAddr> {
+    if let Ok(address) = node.parse::<SocketAddr>() {
+        return Some(address);
+    }
+
+    let ip = node
+        .strip_prefix('[')
+        .and_then(|ip| ip.strip_suffix(']'))
+        .unwrap_or(node);
+
+    // when there's no port, we don't know it
+    ip.parse::<IpAddr>().ok().map(|ip| SocketAddr::new(ip, 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use axum::http::{HeaderMap, HeaderValue};
+    use ipnet::IpNet;
+    use pretty_assertions::assert_eq;
+
+    use crate::utils::forwarded::{UNIX_PEER, client_address, parse_trusted_proxy};
+
+    fn address(address: &str) -> SocketAddr {
+        address.parse().unwrap()
+    }
+
+    fn trusted(proxies: &[&str]) -> Vec<IpNet> {
+        proxies
+            .iter()
+            .map(|proxy| parse_trusted_proxy(proxy).unwrap())
+            .collect()
+    }
+
+    fn header_map(headers: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+
+        for &(name, value) in headers {
+            map.append(name, HeaderValue::from_static(value));
+        }
+
+        map
+    }
+
+    #[test]
+    fn ignores_headers_from_untrusted_peers() {
+        let headers = header_map(&[("x-forwarded-for", "203.0.113.7")]);
+
+        assert_eq!(
+            client_address(
+                address("198.51.100.1:1234"),
+                &headers,
+                &trusted(&["10.0.0.0/8"])
+            ),
+            address("198.51.100.1:1234")
+        );
+    }
+
+    #[test]
+    fn walks_back_through_trusted_proxies() {
+        let trusted = trusted(&["10.0.0.0/8"]);
+
+        let headers = header_map(&[("x-forwarded-for", "192.0.2.1, 203.0.113.7, 10.1.2.3")]);
+
+        // 192.0.2.1 was made up by the client, 203.0.113.7 is who connected to our outer proxy
+        assert_eq!(
+            client_address(address("10.0.0.1:1234"), &headers, &trusted),
+            address("203.0.113.7:0")
+        );
+    }
+
+    #[test]
+    fn prefers_forwarded() {
+        let trusted = trusted(&["10.0.0.1"]);
+
+        let headers = header_map(&[
+            ("x-forwarded-for", "192.0.2.1"),
+            ("x-real-ip", "192.0.2.2"),
+            ("forwarded", r#"for="[2001:db8:cafe::17]:4711";proto=https"#),
+            ("forwarded", "by=10.0.0.1;For=203.0.113.7:47011"),
+        ]);
+
+        assert_eq!(
+            client_address(address("10.0.0.1:1234"), &headers, &trusted),
+            address("203.0.113.7:47011")
+        );
+
+        let headers = header_map(&[("forwarded", r#"for="[2001:db8:cafe::17]""#)]);
+
+        assert_eq!(
+            client_address(address("10.0.0.1:1234"), &headers, &trusted),
+            address("[2001:db8:cafe::17]:0")
+        );
+    }
+
+    #[test]
+    fn falls_back_to_x_real_ip() {
+        let headers = header_map(&[("x-real-ip", "203.0.113.7")]);
+
+        assert_eq!(
+            client_address(address("[::1]:1234"), &headers, &trusted(&["::1/128"])),
+            address("203.0.113.7:0")
+        );
+    }
+
+    #[test]
+    fn stops_at_unknown_hops() {
+        let headers = header_map(&[("forwarded", "for=203.0.113.7, for=unknown, for=10.0.0.2")]);
+
+        assert_eq!(
+            client_address(
+                address("10.0.0.1:1234"),
+                &headers,
+                &trusted(&["10.0.0.0/24"])
+            ),
+            address("10.0.0.2:0")
+        );
+    }
+
+    #[test]
+    fn parses_trusted_proxies() {
+        assert_eq!(
+            parse_trusted_proxy("10.0.0.0/8"),
+            Ok("10.0.0.0/8".parse().unwrap())
+        );
+        assert_eq!(
+            parse_trusted_proxy("10.0.0.1"),
+            Ok("10.0.0.1/32".parse().unwrap())
+        );
+        assert_eq!(
+            parse_trusted_proxy("fd00::/8"),
+            Ok("fd00::/8".parse().unwrap())
+        );
+        assert_eq!(parse_trusted_proxy("proxy").ok(), None, "not an address");
+    }
+
+    #[test]
+    fn trusts_proxies_on_a_unix_socket() {
+        let headers = header_map(&[("x-forwarded-for", "203.0.113.7")]);
+
+        assert_eq!(
+            client_address(UNIX_PEER, &headers, &[]),
+            address("203.0.113.7:0")
+        );
+
+        let headers = header_map(&[("x-forwarded-for", "203.0.113.7, 127.0.0.1")]);
+
+        assert_eq!(
+            client_address(UNIX_PEER, &headers, &[]),
+            address("127.0.0.1:0"),
+            "a hop to 127.0.0.1 isn't the Unix socket"
+        );
+    }
+}
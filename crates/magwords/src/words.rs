@@ -1,22 +1,54 @@
-use std::net::SocketAddr;
+pub mod codec;
+pub mod connections;
+pub mod drags;
+pub mod frame;
+pub mod fridges;
+pub mod grabs;
+pub mod history;
+pub mod layout;
+pub mod liveness;
+pub mod rate_limit;
+pub mod recent;
+pub mod replay;
+pub mod snapshot;
+pub mod sse;
+pub mod undo;
+pub mod word_list;
+
+use std::net::{IpAddr, SocketAddr};
 use std::ops::ControlFlow;
-use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, AtomicUsize};
+use std::sync::{Arc, Mutex, PoisonError};
 use std::time::Duration;
 
-use axum::extract::State;
-use axum::extract::connect_info::ConnectInfo;
-use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
-use axum::response::IntoResponse;
+use axum::extract::ws::{CloseFrame, Message, Utf8Bytes, WebSocket, WebSocketUpgrade, close_code};
+use axum::extract::{Path, Query, State};
+use axum::response::{IntoResponse as _, Response};
+use hashbrown::HashMap;
 use rand::RngExt as _;
 use serde::{Deserialize, Serialize};
 use tokio::sync::{RwLock, broadcast};
 use tokio::time::Instant;
 use tracing::{Level, event};
+use uuid::Uuid;
 
-use crate::states::config::FridgeDimensions;
+use crate::states::config::{Config, FridgeDimensions, MoveLimits, RateLimit};
+use crate::words::codec::{Codec, SUBPROTOCOLS};
+use crate::words::connections::{Admission, ConnectionLimit};
+use crate::words::drags::Drags;
+use crate::words::frame::Frame;
+use crate::words::fridges::Fridges;
+use crate::words::grabs::{GrabOutcome, Grabs};
+use crate::words::history::{History, HistoryEntry, Position};
+use crate::words::liveness::Liveness;
+use crate::words::rate_limit::TokenBucket;
+use crate::words::recent::Recent;
+use crate::words::replay::{ReplayError, ReplayParams};
+use crate::words::snapshot::Snapshot;
+use crate::words::sse::SsePoets;
+use crate::words::undo::{MAX_UNDO, Step, UndoStack};
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
 pub struct MoveEventParams {
     id: usize,
     v: usize,
@@ -24,15 +56,40 @@ pub struct MoveEventParams {
     y: u32,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// An intermediate position of a word being dragged, which isn't persisted.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct DragEventParams {
+    id: usize,
+    x: u32,
+    y: u32,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
 pub struct WordInfo {
     id: usize,
     word: String,
+    /// Bumped on every move, so that moves made against an outdated position can be refused.
+    #[serde(default)]
+    v: usize,
     x: u32,
     y: u32,
 }
 
-#[derive(Debug, Serialize, Clone)]
+impl From<&WordInfo> for MoveEventParams {
+    fn from(word: &WordInfo) -> Self {
+        MoveEventParams {
+            id: word.id,
+            v: word.v,
+            x: word.x,
+            y: word.y,
+        }
+    }
+}
+
+/// Version of the wire protocol, sent in every heartbeat. Clients reload when it doesn't match theirs.
+pub const PROTOCOL_VERSION: u64 = 2;
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
 #[serde(tag = "type", content = "data", rename_all = "lowercase")]
 pub enum ServerMessage {
     Config {
@@ -43,13 +100,58 @@ pub enum ServerMessage {
         count: usize,
     },
     Move(MoveEventParams),
+    /// Words put on the fridge when the word lists grew, after those that were there.
+    Added(Vec<WordInfo>),
+    /// Someone is dragging a word, it'll be followed by a `Move` when they drop it.
+    Drag(DragEventParams),
+    /// Sent to a poet whose move was refused, with where the word actually is.
+    Correction(MoveEventParams),
+    /// The word is being dragged by someone else, and can't be moved until it's released.
+    Grabbed {
+        id: usize,
+    },
+    Released {
+        id: usize,
+    },
     Hup {
         id: u64,
         v: u64,
     },
     Goodbye {},
+    /// Where the poet is in the fridge's numbered broadcasts, to resume from when they reconnect.
+    Sequence {
+        /// Tells this fridge's sequence numbers apart from those of an earlier run, or of an evicted fridge.
+        stream: String,
+        seq: u64,
+    },
+}
+
+impl ServerMessage {
+    /// Whether a poet who missed the message needs it to get the fridge right.
+    /// Those are numbered, and replayed to poets who resume.
+    fn is_resumable(&self) -> bool {
+        matches!(
+            *self,
+            ServerMessage::Move(_)
+                | ServerMessage::Added(_)
+                | ServerMessage::Grabbed { .. }
+                | ServerMessage::Released { .. }
+        )
+    }
+}
+
+impl From<FridgeDimensions> for ServerMessage {
+    fn from(fridge_dimensions: FridgeDimensions) -> Self {
+        ServerMessage::Config {
+            fridge_width: fridge_dimensions.fridge_width,
+            fridge_height: fridge_dimensions.fridge_height,
+        }
+    }
 }
 
+/// A broadcast, and the poet it isn't for, if any.
+type Broadcast = (Option<u64>, Arc<Frame>);
+
 // separate struct to serialize the word list without cloning
 #[derive(Serialize)]
 struct WireWords<'a> {
@@ -57,263 +159,1960 @@ struct WireWords<'a> {
     data: &'a [WordInfo],
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(tag = "type", content = "data", rename_all = "lowercase")]
 enum ClientMessage {
     Move(MoveEventParams),
+    /// Where the poet is dragging a word, the final position comes as a `Move`.
+    Drag(DragEventParams),
+    /// The poet started dragging a word.
+    Grab {
+        id: usize,
+    },
+    /// The poet let go of a word without moving it.
+    Release {
+        id: usize,
+    },
+    /// Undo the poet's last `count` moves, skipping words someone else moved since.
+    Undo {
+        count: usize,
+    },
+    Redo {
+        count: usize,
+    },
     Pong {
-        #[expect(
-            dead_code,
-            reason = "deserialized from wire protocol but not read server-side"
-        )]
         id: u64,
     },
 }
 
 pub struct WsState {
-    broadcast_tx: broadcast::Sender<(Option<u64>, ServerMessage)>,
+    name: String,
+    broadcast_tx: broadcast::Sender<Broadcast>,
+    stream: String,
+    recent: Mutex<Recent>,
     word_list: RwLock<Vec<WordInfo>>,
+    /// Only changed while holding the write lock on [`WsState::word_list`].
+    fridge_dimensions: Mutex<FridgeDimensions>,
+    grabs: Grabs,
+    drags: Drags,
+    history: History,
     poets: AtomicUsize,
     next_client_id: AtomicU64,
+    last_active: Mutex<Instant>,
+    move_limits: Mutex<MoveLimits>,
+    /// Moves left per address, when [`MoveLimits::address`] is set.
+    address_moves: Mutex<HashMap<IpAddr, TokenBucket>>,
+    sse_poets: SsePoets,
 }
 
-impl WsState {
-    pub fn broadcast(&self, exclude: Option<u64>, message: ServerMessage) {
-        let _r = self.broadcast_tx.send((exclude, message));
-    }
+/// What became of a move.
+#[derive(Serialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum MoveOutcome {
+    Moved(Step),
+    /// The move was made against an older version of the word, this is the current one.
+    Stale(MoveEventParams),
+    /// Someone else is dragging the word, this is where it is.
+    Grabbed(MoveEventParams),
+    OutOfBounds,
+    UnknownWord,
 }
 
-pub fn build_ws_state(raw_words: &str, fridge_dimensions: FridgeDimensions) -> Arc<WsState> {
-    let word_list = build_words(raw_words, fridge_dimensions);
-    let (broadcast_tx, _) = broadcast::channel(256);
+/// What reverting a fridge did.
+#[derive(Debug, Serialize)]
+pub struct RevertReport {
+    /// How many words were put back.
+    moved: usize,
+    /// Whether the fridge is back to how it was, or only as far back as the history goes.
+    complete: bool,
+}
 
-    Arc::new(WsState {
-        broadcast_tx,
-        word_list: RwLock::new(word_list),
-        poets: AtomicUsize::new(0),
-        next_client_id: AtomicU64::new(0),
-    })
+/// What became of a drag.
+enum DragOutcome {
+    Recorded,
+    /// Someone else is dragging the word.
+    Grabbed,
+    OutOfBounds,
+    UnknownWord,
 }
 
-fn build_words(
-    words: &str,
-    FridgeDimensions {
-        fridge_width,
-        fridge_height,
-    }: FridgeDimensions,
-) -> Vec<WordInfo> {
-    let mut rng = rand::rng();
+impl WsState {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 
-    words
-        .lines()
-        .enumerate()
-        .map(|(i, line)| WordInfo {
-            id: i,
-            word: line.into(),
-            x: rng.random_range(0..=fridge_width),
-            y: rng.random_range(0..=fridge_height),
-        })
-        .collect::<Vec<_>>()
-}
+    /// How long ago a poet last left (or the fridge was created).
+    pub fn idle_for(&self) -> Duration {
+        self.last_active
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .elapsed()
+    }
 
-pub async fn ws_handler(
-    ws: WebSocketUpgrade,
-    ConnectInfo(address): ConnectInfo<SocketAddr>,
-    State(fridge_dimensions): State<FridgeDimensions>,
-    State(ws_state): State<Arc<WsState>>,
-) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_socket(socket, ws_state, fridge_dimensions, address))
-}
+    fn touch(&self) {
+        *self
+            .last_active
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Instant::now();
+    }
+
+    pub fn broadcast(&self, exclude: Option<u64>, message: ServerMessage) {
+        if !message.is_resumable() {
+            self.broadcast_frame(exclude, Arc::new(Frame::new(message)));
+            return;
+        }
 
-// max time to wait for a pong before considering the client stale
-const PONG_TIMEOUT: Duration = Duration::from_secs(5);
+        let mut recent = self.recent.lock().unwrap_or_else(PoisonError::into_inner);
 
-async fn handle_outbound(
-    result: Result<(Option<u64>, ServerMessage), broadcast::error::RecvError>,
-    client_id: u64,
-    address: SocketAddr,
-    socket: &mut WebSocket,
-    last_pong: Instant,
-) -> ControlFlow<()> {
-    match result {
-        Ok((exclude, message)) => {
-            if exclude == Some(client_id) {
-                return ControlFlow::Continue(());
-            }
+        // send while holding the lock, so that the sequence numbers go out in order
+        let frame = recent.record(message);
 
-            // on hup, check if the client responded to a previous heartbeat
-            if matches!(message, ServerMessage::Hup { .. }) && last_pong.elapsed() > PONG_TIMEOUT {
-                event!(Level::TRACE, client_id, %address, "client timed out");
-                return ControlFlow::Break(());
-            }
+        let _r = self.broadcast_tx.send((exclude, frame));
+    }
 
-            let is_goodbye = matches!(message, ServerMessage::Goodbye { .. });
+    /// Like [`WsState::broadcast`], for a message that's already on its way to other fridges,
+    /// and that a poet who resumes doesn't need.
+    pub fn broadcast_frame(&self, exclude: Option<u64>, frame: Arc<Frame>) {
+        let _r = self.broadcast_tx.send((exclude, frame));
+    }
 
-            let json = match serde_json::to_string(&message) {
-                Ok(json) => json,
-                Err(error) => {
-                    event!(Level::ERROR, ?error, ?message, client_id, %address, "failed to serialize message, this is a bug");
+    /// Subscribes to the fridge's broadcasts, returning where the subscription starts,
+    /// and what a poet resuming from `resume` missed, when we still remember all of it.
+    fn subscribe(
+        &self,
+        resume: Option<&Resume>,
+    ) -> (
+        broadcast::Receiver<Broadcast>,
+        ServerMessage,
+        Option<Vec<Arc<Frame>>>,
+    ) {
+        let recent = self.recent.lock().unwrap_or_else(PoisonError::into_inner);
 
-                    return ControlFlow::Break(());
-                },
-            };
+        let missed = resume
+            .filter(|resume| resume.stream == self.stream)
+            .and_then(|resume| recent.since(resume.seq));
 
-            if let Err(error) = socket.send(Message::text(json)).await {
-                event!(Level::TRACE, ?error, client_id, %address, "failed to send message");
-                return ControlFlow::Break(());
-            }
+        let sequence = ServerMessage::Sequence {
+            stream: self.stream.clone(),
+            seq: recent.seq(),
+        };
 
-            if is_goodbye {
-                ControlFlow::Break(())
-            } else {
-                ControlFlow::Continue(())
-            }
-        },
-        Err(broadcast::error::RecvError::Lagged(count)) => {
-            event!(
-                Level::TRACE,
-                client_id,
-                %address,
-                count,
-                "client lagged, disconnecting"
-            );
-            ControlFlow::Break(())
-        },
-        Err(broadcast::error::RecvError::Closed) => ControlFlow::Break(()),
+        (self.broadcast_tx.subscribe(), sequence, missed)
     }
-}
 
-async fn handle_inbound(
-    result: Option<Result<Message, axum::Error>>,
-    client_id: u64,
-    fridge_dimensions: FridgeDimensions,
-    address: SocketAddr,
-    state: &WsState,
-    last_pong: &mut Instant,
-) -> ControlFlow<()> {
-    match result {
-        Some(Ok(Message::Text(text))) => {
-            match serde_json::from_str::<ClientMessage>(&text) {
-                Ok(ClientMessage::Move(move_event)) => {
-                    if move_event.x > fridge_dimensions.fridge_width
-                        || move_event.y > fridge_dimensions.fridge_height
-                    {
-                        event!(Level::WARN, client_id, %address, x = move_event.x, y = move_event.y, "out of bounds move, disconnecting");
-                        return ControlFlow::Break(());
-                    }
+    /// Moves a word and tells everyone but `client_id`, provided the move was made
+    /// against the word's current version.
+    async fn apply_move(&self, move_event: MoveEventParams, client_id: Option<u64>) -> MoveOutcome {
+        let mut lock = self.word_list.write().await;
 
-                    let mut lock = state.word_list.write().await;
+        let fridge_dimensions = self.fridge_dimensions();
 
-                    if let Some(word) = lock.get_mut(move_event.id) {
-                        word.x = move_event.x;
-                        word.y = move_event.y;
+        if move_event.x > fridge_dimensions.fridge_width
+            || move_event.y > fridge_dimensions.fridge_height
+        {
+            return MoveOutcome::OutOfBounds;
+        }
 
-                        state.broadcast(Some(client_id), ServerMessage::Move(move_event));
-                    } else {
-                        event!(Level::WARN, client_id, %address, id = move_event.id, "invalid word id, disconnecting");
-                        return ControlFlow::Break(());
-                    }
-                },
-                Ok(ClientMessage::Pong { .. }) => {
-                    *last_pong = Instant::now();
-                },
-                Err(error) => {
-                    event!(Level::TRACE, ?error, client_id, %address, "invalid message received");
-                },
-            }
+        let Some(word) = lock.get_mut(move_event.id) else {
+            return MoveOutcome::UnknownWord;
+        };
 
-            ControlFlow::Continue(())
-        },
-        Some(Ok(Message::Close(_))) | None => ControlFlow::Break(()),
-        Some(Err(error)) => {
-            event!(Level::TRACE, ?error, client_id, %address, "websocket read error");
-            ControlFlow::Break(())
-        },
-        Some(Ok(_)) => ControlFlow::Continue(()),
-    }
-}
+        if self.grabs.is_held_by_other(word.id, client_id) {
+            return MoveOutcome::Grabbed(MoveEventParams::from(&*word));
+        }
 
-async fn handle_socket(
-    mut socket: WebSocket,
-    state: Arc<WsState>,
-    fridge_dimensions: FridgeDimensions,
-    address: SocketAddr,
-) {
-    let client_id = state
-        .next_client_id
-        .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        if move_event.v != word.v {
+            return MoveOutcome::Stale(MoveEventParams::from(&*word));
+        }
 
-    event!(Level::DEBUG, client_id, %address, "Client connected");
+        let step = Step {
+            id: word.id,
+            from: Position {
+                x: word.x,
+                y: word.y,
+            },
+            to: Position {
+                x: move_event.x,
+                y: move_event.y,
+            },
+        };
 
-    // send fridge dimensions
-    {
-        let config = ServerMessage::Config {
-            fridge_width: fridge_dimensions.fridge_width,
-            fridge_height: fridge_dimensions.fridge_height,
+        word.v += 1;
+        word.x = move_event.x;
+        word.y = move_event.y;
+
+        self.history.record(HistoryEntry {
+            id: step.id,
+            from: step.from,
+            to: step.to,
+            client_id,
+            at: history::now(),
+        });
+
+        // the word has landed, a position from while it was in the air would only confuse
+        self.drags.discard(word.id);
+
+        // broadcast while holding the lock, so that everyone sees the versions in order
+        self.broadcast(
+            client_id,
+            ServerMessage::Move(MoveEventParams {
+                v: word.v,
+                ..move_event
+            }),
+        );
+
+        // dropping a word lets go of it
+        if let Some(client_id) = client_id {
+            self.release(word.id, client_id);
+        }
+
+        MoveOutcome::Moved(step)
+    }
+
+    /// Moves a word on behalf of someone who isn't on the websocket, such as a bot using the REST API,
+    /// and tells everyone. Without a `v`, the move is made against whatever version the word is at.
+    pub async fn move_from_api(
+        &self,
+        id: usize,
+        v: Option<usize>,
+        position: Position,
+    ) -> MoveOutcome {
+        let v = match v {
+            Some(v) => v,
+            None => match self.word_list.read().await.get(id) {
+                Some(word) => word.v,
+                None => return MoveOutcome::UnknownWord,
+            },
         };
 
-        let json = match serde_json::to_string(&config) {
-            Ok(json) => json,
-            Err(error) => {
-                event!(Level::ERROR, ?error, client_id, %address, "failed to serialize config");
-                return;
+        self.apply_move(
+            MoveEventParams {
+                id,
+                v,
+                x: position.x,
+                y: position.y,
             },
+            None,
+        )
+        .await
+    }
+
+    /// Moves a word on behalf of `client_id`, provided it's still where the poet left it
+    /// and nobody else is dragging it, and tells everyone. Returns whether it did.
+    async fn move_word(&self, step: Step, client_id: u64) -> bool {
+        let mut lock = self.word_list.write().await;
+
+        let Some(word) = lock.get_mut(step.id) else {
+            return false;
         };
 
-        if let Err(error) = socket.send(Message::text(json)).await {
-            event!(Level::TRACE, ?error, client_id, %address, "failed to send config");
-            return;
+        if (Position {
+            x: word.x,
+            y: word.y,
+        }) != step.from
+            || self.grabs.is_held_by_other(word.id, Some(client_id))
+            // the fridge may have shrunk since
+            || layout::clamp(step.to, self.fridge_dimensions()) != step.to
+        {
+            return false;
         }
+
+        self.place(word, step.to, Some(client_id));
+
+        true
     }
 
-    // send initial word list to this client
-    {
-        let json = {
-            let words = state.word_list.read().await;
-            serde_json::to_string(&WireWords {
-                r#type: "words",
-                data: &words,
-            })
-        };
+    /// Puts the words that moved after `at` back where they were at that time, and tells everyone.
+    pub async fn revert_to(&self, at: u64) -> RevertReport {
+        let mut lock = self.word_list.write().await;
 
-        if let Ok(json) = json {
-            if let Err(error) = socket.send(Message::text(json)).await {
-                event!(Level::TRACE, ?error, client_id, %address, "failed to send words");
-                return;
+        let revert = self.history.revert_to(at);
+
+        let mut positions = revert.positions.into_iter().collect::<Vec<_>>();
+        positions.sort_unstable_by_key(|&(id, _)| id);
+
+        let mut moved = 0;
+
+        let fridge_dimensions = self.fridge_dimensions();
+
+        for (id, position) in positions {
+            let Some(word) = lock.get_mut(id) else {
+                continue;
+            };
+
+            // the fridge may have shrunk since
+            let position = layout::clamp(position, fridge_dimensions);
+
+            if (Position {
+                x: word.x,
+                y: word.y,
+            }) == position
+            {
+                continue;
             }
+
+            self.place(word, position, None);
+            moved += 1;
+        }
+
+        RevertReport {
+            moved,
+            complete: revert.complete,
         }
     }
 
-    let mut broadcast_rx = state.broadcast_tx.subscribe();
+    pub async fn words(&self) -> Vec<WordInfo> {
+        self.word_list.read().await.clone()
+    }
 
-    // increment poets and broadcast to all
-    {
-        let new_count = state
+    pub async fn word(&self, id: usize) -> Option<WordInfo> {
+        self.word_list.read().await.get(id).cloned()
+    }
+
+    /// How many poets are on the fridge right now.
+    pub fn poets(&self) -> usize {
+        self.poets.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Counts a poet walking up to the fridge, and tells everyone.
+    fn join(&self) {
+        let new_count = self
             .poets
             .fetch_add(1, std::sync::atomic::Ordering::Relaxed)
             + 1;
-        state.broadcast(None, ServerMessage::Poets { count: new_count });
+        self.broadcast(None, ServerMessage::Poets { count: new_count });
     }
 
-    let mut last_pong = Instant::now();
+    /// Counts a poet walking away from the fridge, and tells everyone.
+    fn leave(&self) {
+        let new_count = self
+            .poets
+            .fetch_sub(1, std::sync::atomic::Ordering::Relaxed)
+            - 1;
+        self.broadcast(None, ServerMessage::Poets { count: new_count });
+        self.touch();
 
-    loop {
-        let flow = tokio::select! {
-            result = broadcast_rx.recv() => handle_outbound(result, client_id, address, &mut socket, last_pong).await,
-            result = socket.recv() => handle_inbound(result, client_id, fridge_dimensions, address, &state, &mut last_pong).await,
+        // addresses that slowed down are as good as new
+        self.address_moves
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|_, bucket| !bucket.is_full());
+    }
+
+    /// Takes `count` moves from those left to the poets at `ip`, when moves are limited per address.
+    fn take_address_moves(&self, ip: IpAddr, count: u32) -> bool {
+        let Some(limit) = self.move_limits().address else {
+            return true;
         };
 
-        if flow.is_break() {
-            break;
+        self.address_moves
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(ip)
+            .or_insert_with(|| TokenBucket::new(limit))
+            .try_take(count)
+    }
+
+    /// The moves made from `from` up to and including `to`, oldest first.
+    pub fn history_between(&self, from: u64, to: u64) -> Vec<HistoryEntry> {
+        self.history.between(from, to)
+    }
+
+    /// The words as they were right before `at`, as far as the history goes back,
+    /// and whether it went back far enough.
+    pub async fn words_before(&self, at: u64) -> (Vec<WordInfo>, bool) {
+        let mut words = self.word_list.read().await.clone();
+
+        let revert = self.history.revert_to(at.saturating_sub(1));
+
+        for (id, position) in revert.positions {
+            if let Some(word) = words.get_mut(id) {
+                word.x = position.x;
+                word.y = position.y;
+            }
         }
+
+        (words, revert.complete)
     }
 
-    // client disconnected, clean up
-    let new_count = state
-        .poets
-        .fetch_sub(1, std::sync::atomic::Ordering::Relaxed)
-        - 1;
-    state.broadcast(None, ServerMessage::Poets { count: new_count });
+    /// Puts a word somewhere other than where its poet asked for, so everyone is told, the poet included.
+    fn place(&self, word: &mut WordInfo, to: Position, client_id: Option<u64>) {
+        self.history.record(HistoryEntry {
+            id: word.id,
+            from: Position {
+                x: word.x,
+                y: word.y,
+            },
+            to,
+            client_id,
+            at: history::now(),
+        });
+
+        word.v += 1;
+        word.x = to.x;
+        word.y = to.y;
+
+        self.drags.discard(word.id);
+
+        self.broadcast(None, ServerMessage::Move(MoveEventParams::from(&*word)));
+    }
+
+    /// Records where `client_id` is dragging a word, to be sent out with the next [`WsState::flush_drags`].
+    async fn drag(&self, drag: DragEventParams, client_id: u64) -> DragOutcome {
+        let fridge_dimensions = self.fridge_dimensions();
+
+        if drag.x > fridge_dimensions.fridge_width || drag.y > fridge_dimensions.fridge_height {
+            return DragOutcome::OutOfBounds;
+        }
+
+        if drag.id >= self.word_list.read().await.len() {
+            return DragOutcome::UnknownWord;
+        }
+
+        if self.grabs.is_held_by_other(drag.id, Some(client_id)) {
+            return DragOutcome::Grabbed;
+        }
+
+        self.grabs.touch(drag.id, client_id);
+        self.drags.record(client_id, drag);
+
+        DragOutcome::Recorded
+    }
+
+    /// Tells everyone but the dragger where the words being dragged are now.
+    pub fn flush_drags(&self) {
+        self.drags.flush(|client_id, drag| {
+            self.broadcast(Some(client_id), ServerMessage::Drag(drag));
+        });
+    }
+
+    async fn grab(&self, id: usize, client_id: u64) -> GrabOutcome {
+        if id >= self.word_list.read().await.len() {
+            return GrabOutcome::UnknownWord;
+        }
+
+        let outcome = self.grabs.grab(id, client_id);
+
+        if let GrabOutcome::Grabbed { released } = outcome {
+            if let Some(released) = released {
+                self.broadcast(Some(client_id), ServerMessage::Released { id: released });
+            }
+
+            self.broadcast(Some(client_id), ServerMessage::Grabbed { id });
+        }
+
+        outcome
+    }
+
+    fn release(&self, id: usize, client_id: u64) {
+        if self.grabs.release(id, client_id) {
+            self.broadcast(Some(client_id), ServerMessage::Released { id });
+        }
+    }
+
+    /// Lets go of the words held for too long.
+    pub fn expire_grabs(&self) {
+        for id in self.grabs.expire() {
+            self.broadcast(None, ServerMessage::Released { id });
+        }
+    }
+
+    pub async fn snapshot(&self) -> Snapshot {
+        let words = self.word_list.read().await;
+
+        Snapshot {
+            fridge_dimensions: self.fridge_dimensions(),
+            words: words.clone(),
+        }
+    }
+
+    pub fn fridge_dimensions(&self) -> FridgeDimensions {
+        *self
+            .fridge_dimensions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Resizes the fridge, and tells everyone. Words that end up off the fridge are moved onto its edge,
+    /// returns how many.
+    pub async fn set_fridge_dimensions(&self, fridge_dimensions: FridgeDimensions) -> usize {
+        // so that no move is checked against the old size once everyone was told about the new one
+        let mut lock = self.word_list.write().await;
+
+        *self
+            .fridge_dimensions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = fridge_dimensions;
+
+        self.broadcast(None, ServerMessage::from(fridge_dimensions));
+
+        layout::clamp_all(&mut lock, fridge_dimensions, |word, to| {
+            self.place(word, to, None);
+        })
+    }
+
+    fn move_limits(&self) -> MoveLimits {
+        *self
+            .move_limits
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Changes how fast poets may move words, those on the fridge already included.
+    pub fn set_move_limits(&self, move_limits: MoveLimits) {
+        *self
+            .move_limits
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = move_limits;
+
+        let mut address_moves = self
+            .address_moves
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        match move_limits.address {
+            Some(limit) => {
+                for bucket in address_moves.values_mut() {
+                    bucket.set_limit(limit);
+                }
+            },
+            None => address_moves.clear(),
+        }
+    }
+
+    /// Scatters the words at the end of `words` that aren't on the fridge yet, and tells everyone.
+    ///
+    /// Returns how many were added, or `None` when `words` doesn't start with the words on the fridge,
+    /// as words can't be taken away or swapped for others while poets are moving them.
+    pub async fn add_words(&self, words: &[String]) -> Option<usize> {
+        let mut lock = self.word_list.write().await;
+
+        let new_words = words.get(lock.len()..)?;
 
-    event!(Level::TRACE, client_id, %address, "Client disconnected");
+        if lock
+            .iter()
+            .zip(words)
+            .any(|(word_info, word)| word_info.word != *word)
+        {
+            return None;
+        }
+
+        if new_words.is_empty() {
+            return Some(0);
+        }
+
+        let added = build_words(new_words, lock.len(), self.fridge_dimensions());
+
+        lock.extend(added.iter().cloned());
+
+        let count = added.len();
+
+        // broadcast while holding the lock, so that no move of a new word goes out before it
+        self.broadcast(None, ServerMessage::Added(added));
+
+        Some(count)
+    }
+}
+
+pub fn build_ws_state(
+    name: &str,
+    words: &[String],
+    fridge_dimensions: FridgeDimensions,
+    snapshot: Option<Snapshot>,
+    history_size: usize,
+    broadcast_capacity: usize,
+    move_limits: MoveLimits,
+) -> Arc<WsState> {
+    let mut word_list = match snapshot {
+        Some(snapshot) if snapshot.matches(words) => {
+            event!(
+                Level::INFO,
+                fridge = name,
+                "Restoring fridge layout from snapshot"
+            );
+
+            snapshot.words
+        },
+        Some(snapshot) if snapshot.is_prefix_of(words) => {
+            event!(
+                Level::INFO,
+                fridge = name,
+                "Restoring fridge layout from snapshot, and scattering the words added since"
+            );
+
+            let mut word_list = snapshot.words;
+
+            let added = words.get(word_list.len()..).unwrap_or_default();
+
+            word_list.extend(build_words(added, word_list.len(), fridge_dimensions));
+
+            word_list
+        },
+        Some(_) => {
+            event!(
+                Level::WARN,
+                fridge = name,
+                "Snapshot was taken with a different word list, scattering words instead"
+            );
+
+            build_words(words, 0, fridge_dimensions)
+        },
+        None => build_words(words, 0, fridge_dimensions),
+    };
+
+    // the fridge may have shrunk since the snapshot was taken, or it was edited by hand
+    let moved = layout::clamp_all(&mut word_list, fridge_dimensions, |word, to| {
+        word.x = to.x;
+        word.y = to.y;
+    });
+
+    if moved > 0 {
+        event!(
+            Level::INFO,
+            fridge = name,
+            moved,
+            "Moved words that were off the fridge onto its edge"
+        );
+    }
+
+    let (broadcast_tx, _) = broadcast::channel(broadcast_capacity);
+
+    Arc::new(WsState {
+        name: name.to_owned(),
+        broadcast_tx,
+        stream: Uuid::now_v7().to_string(),
+        recent: Mutex::new(Recent::default()),
+        word_list: RwLock::new(word_list),
+        fridge_dimensions: Mutex::new(fridge_dimensions),
+        grabs: Grabs::default(),
+        drags: Drags::default(),
+        history: History::new(history_size),
+        poets: AtomicUsize::new(0),
+        next_client_id: AtomicU64::new(0),
+        last_active: Mutex::new(Instant::now()),
+        move_limits: Mutex::new(move_limits),
+        address_moves: Mutex::new(HashMap::new()),
+        sse_poets: SsePoets::default(),
+    })
+}
+
+/// Scatters `words`, numbered from `first_id`.
+fn build_words(
+    words: &[String],
+    first_id: usize,
+    FridgeDimensions {
+        fridge_width,
+        fridge_height,
+    }: FridgeDimensions,
+) -> Vec<WordInfo> {
+    let mut rng = rand::rng();
+
+    words
+        .iter()
+        .zip(first_id..)
+        .map(|(word, id)| WordInfo {
+            id,
+            word: word.clone(),
+            v: 0,
+            x: rng.random_range(0..=fridge_width),
+            y: rng.random_range(0..=fridge_height),
+        })
+        .collect::<Vec<_>>()
+}
+
+/// Query parameters of `/ws` and `/ws/{fridge}`.
+#[derive(Deserialize)]
+pub struct WsParams {
+    /// Watch the fridge's history, as described by [`ReplayParams`], instead of joining it.
+    #[serde(default)]
+    replay: bool,
+    /// Resume from the latest [`ServerMessage::Sequence`] the poet got, instead of getting the whole fridge.
+    stream: Option<String>,
+    seq: Option<u64>,
+}
+
+impl WsParams {
+    fn resume(&self) -> Option<Resume> {
+        match (&self.stream, self.seq) {
+            (&Some(ref stream), Some(seq)) => Some(Resume {
+                stream: stream.clone(),
+                seq,
+            }),
+            (&Some(_), None) | (&None, _) => None,
+        }
+    }
+}
+
+/// Where a reconnecting poet left off.
+struct Resume {
+    stream: String,
+    seq: u64,
+}
+
+/// # Errors
+/// * The replay parameters are invalid
+pub async fn ws_handler(
+    ws: WebSocketUpgrade,
+    admission: Admission,
+    Query(params): Query<WsParams>,
+    Query(replay_params): Query<ReplayParams>,
+    State(config): State<Arc<Config>>,
+    State(fridges): State<Arc<Fridges>>,
+) -> Result<Response, ReplayError> {
+    let ws_state = fridges.default_fridge();
+
+    upgrade(ws, ws_state, config, admission, &params, &replay_params)
+}
+
+pub async fn fridge_ws_handler(
+    ws: WebSocketUpgrade,
+    Path(fridge): Path<String>,
+    admission: Admission,
+    Query(params): Query<WsParams>,
+    Query(replay_params): Query<ReplayParams>,
+    State(config): State<Arc<Config>>,
+    State(fridges): State<Arc<Fridges>>,
+) -> Response {
+    let ws_state = match fridges.get_or_create(&fridge).await {
+        Ok(ws_state) => ws_state,
+        Err(error) => return error.into_response(),
+    };
+
+    upgrade(ws, ws_state, config, admission, &params, &replay_params).into_response()
+}
+
+/// Joins the poet to the fridge, or starts a replay of it, or turns them away when there are too many.
+fn upgrade(
+    ws: WebSocketUpgrade,
+    ws_state: Arc<WsState>,
+    config: Arc<Config>,
+    Admission {
+        address,
+        connection,
+    }: Admission,
+    params: &WsParams,
+    replay_params: &ReplayParams,
+) -> Result<Response, ReplayError> {
+    let ws = ws.protocols(SUBPROTOCOLS);
+    let codec = Codec::from_protocol(ws.selected_protocol());
+
+    let connection = match connection {
+        Ok(connection) => connection,
+        Err(limit) => {
+            event!(Level::INFO, %address, ?limit, "Turning poet away");
+
+            return Ok(ws.on_upgrade(move |socket| turn_away(socket, limit)));
+        },
+    };
+
+    if params.replay {
+        let replay = replay_params.replay()?;
+
+        return Ok(ws.on_upgrade(move |socket| async move {
+            replay::handle_replay(socket, ws_state, address, codec, replay).await;
+
+            // counted until the viewer is gone
+            drop(connection);
+        }));
+    }
+
+    let resume = params.resume();
+
+    Ok(ws.on_upgrade(move |socket| async move {
+        handle_socket(socket, ws_state, &config, address, codec, resume).await;
+
+        // counted until the poet is gone
+        drop(connection);
+    }))
+}
+
+/// Tells the poet why they can't join, for them to try again later.
+async fn turn_away(mut socket: WebSocket, limit: ConnectionLimit) {
+    let close = Message::Close(Some(CloseFrame {
+        code: close_code::AGAIN,
+        reason: Utf8Bytes::from_static(limit.reason()),
+    }));
+
+    if let Err(error) = socket.send(close).await {
+        event!(Level::TRACE, ?error, "failed to send close frame");
+    }
+}
+
+async fn handle_outbound(
+    result: Result<Broadcast, broadcast::error::RecvError>,
+    broadcast_rx: &mut broadcast::Receiver<Broadcast>,
+    state: &WsState,
+    client_id: u64,
+    address: SocketAddr,
+    socket: &mut WebSocket,
+    session: &mut Session,
+) -> ControlFlow<()> {
+    match result {
+        Ok((exclude, frame)) => {
+            if exclude == Some(client_id) {
+                return ControlFlow::Continue(());
+            }
+
+            // on hup, check if the client responded to a previous heartbeat in time
+            if matches!(frame.message(), &ServerMessage::Hup { .. })
+                && !session.liveness.heartbeat()
+            {
+                event!(Level::TRACE, client_id, %address, "client timed out");
+                return ControlFlow::Break(());
+            }
+
+            let is_goodbye = matches!(frame.message(), &ServerMessage::Goodbye { .. });
+
+            let flow = send_frames(socket, &[frame], session.codec, client_id, address).await;
+
+            if is_goodbye {
+                ControlFlow::Break(())
+            } else {
+                flow
+            }
+        },
+        Err(broadcast::error::RecvError::Lagged(count)) => {
+            event!(
+                Level::TRACE,
+                client_id,
+                %address,
+                count,
+                "client lagged, catching up"
+            );
+
+            let Some(messages) = catch_up(state, broadcast_rx, session.codec).await else {
+                return ControlFlow::Break(());
+            };
+
+            for message in messages {
+                if let Err(error) = socket.send(message).await {
+                    event!(Level::TRACE, ?error, client_id, %address, "failed to send message");
+                    return ControlFlow::Break(());
+                }
+            }
+
+            ControlFlow::Continue(())
+        },
+        Err(broadcast::error::RecvError::Closed) => ControlFlow::Break(()),
+    }
+}
+
+/// Gets a poet who fell behind back on track: skips the rest of their backlog on `broadcast_rx`,
+/// and returns a fresh copy of the fridge to send them instead, encoded with `codec`.
+///
+/// Shared by the websocket and SSE, which send the messages their own way. The messages are
+/// returned rather than handed to an async closure, which would keep their tasks from being `Send`.
+///
+/// `None` when a message can't be encoded, which is a bug, and logged.
+async fn catch_up(
+    state: &WsState,
+    broadcast_rx: &mut broadcast::Receiver<Broadcast>,
+    codec: Codec,
+) -> Option<Vec<Message>> {
+    let (resubscribed, sequence, _) = state.subscribe(None);
+    *broadcast_rx = resubscribed;
+
+    // the fridge may have been resized in the meantime
+    let config = codec.encode(&ServerMessage::from(state.fridge_dimensions()));
+
+    // encoded before sending, so that moves don't wait on a slow poet
+    let words = {
+        let words = state.word_list.read().await;
+
+        codec.encode(&WireWords {
+            r#type: "words",
+            data: &words,
+        })
+    };
+
+    let grabs = state
+        .grabs
+        .held_words()
+        .into_iter()
+        .map(|id| codec.encode(&ServerMessage::Grabbed { id }));
+
+    let poets = ServerMessage::Poets {
+        count: state.poets(),
+    };
+
+    [config, words]
+        .into_iter()
+        .chain(grabs)
+        .chain([codec.encode(&sequence), codec.encode(&poets)])
+        .collect::<Result<Vec<_>, _>>()
+        .inspect_err(|error| {
+            event!(
+                Level::ERROR,
+                ?error,
+                fridge = state.name,
+                "failed to serialize message, this is a bug"
+            );
+        })
+        .ok()
+}
+
+/// Sends a message to a single client.
+async fn send_message<T>(
+    socket: &mut WebSocket,
+    message: &T,
+    codec: Codec,
+    client_id: u64,
+    address: SocketAddr,
+) -> ControlFlow<()>
+where
+    T: Serialize + ?Sized,
+{
+    let message = match codec.encode(message) {
+        Ok(message) => message,
+        Err(error) => {
+            event!(Level::ERROR, ?error, client_id, %address, "failed to serialize message, this is a bug");
+
+            return ControlFlow::Break(());
+        },
+    };
+
+    if let Err(error) = socket.send(message).await {
+        event!(Level::TRACE, ?error, client_id, %address, "failed to send message");
+        return ControlFlow::Break(());
+    }
+
+    ControlFlow::Continue(())
+}
+
+/// Sends broadcasts to a single client, encoded by whichever client with the same codec got there first.
+async fn send_frames(
+    socket: &mut WebSocket,
+    frames: &[Arc<Frame>],
+    codec: Codec,
+    client_id: u64,
+    address: SocketAddr,
+) -> ControlFlow<()> {
+    for frame in frames {
+        let Some(message) = frame.encoded(codec) else {
+            return ControlFlow::Break(());
+        };
+
+        if let Err(error) = socket.send(message).await {
+            event!(Level::TRACE, ?error, client_id, %address, "failed to send message");
+            return ControlFlow::Break(());
+        }
+    }
+
+    ControlFlow::Continue(())
+}
+
+/// Sends a poet the words on the fridge, and which of them are being dragged.
+async fn send_fridge(
+    socket: &mut WebSocket,
+    state: &WsState,
+    codec: Codec,
+    client_id: u64,
+    address: SocketAddr,
+) -> ControlFlow<()> {
+    let message = {
+        let words = state.word_list.read().await;
+
+        codec.encode(&WireWords {
+            r#type: "words",
+            data: &words,
+        })
+    };
+
+    match message {
+        Ok(message) => {
+            if let Err(error) = socket.send(message).await {
+                event!(Level::TRACE, ?error, client_id, %address, "failed to send words");
+                return ControlFlow::Break(());
+            }
+        },
+        Err(error) => {
+            event!(Level::ERROR, ?error, client_id, %address, "failed to serialize words");
+            return ControlFlow::Break(());
+        },
+    }
+
+    for id in state.grabs.held_words() {
+        send_message(
+            socket,
+            &ServerMessage::Grabbed { id },
+            codec,
+            client_id,
+            address,
+        )
+        .await?;
+    }
+
+    ControlFlow::Continue(())
+}
+
+/// What we keep track of for a single connection.
+struct Session {
+    /// Whether the poet answers heartbeats, before we consider them stale.
+    liveness: Liveness,
+    undo: UndoStack,
+    codec: Codec,
+    /// Moves left to the poet.
+    moves: TokenBucket,
+    /// Moves the poet may have dropped before they're disconnected.
+    dropped_moves: TokenBucket,
+}
+
+/// Takes `count` moves from those left to the poet, and to their address.
+///
+/// `Continue(false)` when the poet is going too fast, and their moves should be dropped.
+/// `Break` when they keep at it.
+fn take_moves(
+    state: &WsState,
+    session: &mut Session,
+    count: u32,
+    client_id: u64,
+    address: SocketAddr,
+) -> ControlFlow<(), bool> {
+    // the limits may have been reloaded since the poet joined
+    let move_limits = state.move_limits();
+
+    session.moves.set_limit(move_limits.poet);
+    session
+        .dropped_moves
+        .set_limit(dropped_moves_limit(move_limits));
+
+    // the poet's moves are only taken once their address has moves left, so they aren't lost for nothing
+    if session.moves.can_take(count)
+        && state.take_address_moves(address.ip(), count)
+        && session.moves.try_take(count)
+    {
+        return ControlFlow::Continue(true);
+    }
+
+    if !session.dropped_moves.try_take(1) {
+        event!(Level::WARN, client_id, %address, "too many moves, disconnecting");
+        return ControlFlow::Break(());
+    }
+
+    event!(Level::DEBUG, client_id, %address, count, "too many moves, dropping");
+
+    ControlFlow::Continue(false)
+}
+
+/// Poets may drop `max_dropped` moves a minute, before they're disconnected.
+fn dropped_moves_limit(move_limits: MoveLimits) -> RateLimit {
+    RateLimit {
+        per_second: f64::from(move_limits.max_dropped) / 60.0,
+        burst: move_limits.max_dropped,
+    }
+}
+
+/// How many moves an undo or redo of `count` moves goes back: no more than `MAX_UNDO`, nor than
+/// poets may make in quick succession, or it could never be afforded.
+fn undo_count(count: usize, limits: MoveLimits) -> usize {
+    let burst = limits.address.map_or(limits.poet.burst, |address| {
+        address.burst.min(limits.poet.burst)
+    });
+
+    count
+        .min(MAX_UNDO)
+        .min(usize::try_from(burst).unwrap_or(usize::MAX))
+}
+
+/// The moves an undo or redo of `count` moves costs, see [`undo_count`].
+fn undo_cost(count: usize) -> u32 {
+    u32::try_from(count.max(1)).unwrap_or(u32::MAX)
+}
+
+/// Undoes up to `count` of the poet's moves, returning how many were undone.
+/// Moves of words that were moved by someone else since can't be undone, and are skipped.
+async fn undo(state: &WsState, stack: &mut UndoStack, client_id: u64, count: usize) -> usize {
+    let mut undone = 0;
+
+    while undone < count.min(MAX_UNDO) {
+        let Some(step) = stack.undo() else {
+            break;
+        };
+
+        if state.move_word(step, client_id).await {
+            stack.undone(step);
+            undone += 1;
+        }
+    }
+
+    undone
+}
+
+/// Redoes up to `count` of the poet's undone moves, returning how many were redone.
+async fn redo(state: &WsState, stack: &mut UndoStack, client_id: u64, count: usize) -> usize {
+    let mut redone = 0;
+
+    while redone < count.min(MAX_UNDO) {
+        let Some(step) = stack.redo() else {
+            break;
+        };
+
+        if state.move_word(step, client_id).await {
+            stack.redone(step);
+            redone += 1;
+        }
+    }
+
+    redone
+}
+
+async fn handle_move(
+    move_event: MoveEventParams,
+    client_id: u64,
+    address: SocketAddr,
+    state: &WsState,
+    socket: &mut WebSocket,
+    session: &mut Session,
+) -> ControlFlow<()> {
+    let (id, x, y) = (move_event.id, move_event.x, move_event.y);
+
+    if !take_moves(state, session, 1, client_id, address)? {
+        // put the word back where it is for everyone else
+        return match state.word(id).await {
+            Some(word) => {
+                send_message(
+                    socket,
+                    &ServerMessage::Correction(MoveEventParams::from(&word)),
+                    session.codec,
+                    client_id,
+                    address,
+                )
+                .await
+            },
+            None => ControlFlow::Continue(()),
+        };
+    }
+
+    match state.apply_move(move_event, Some(client_id)).await {
+        MoveOutcome::Moved(step) => {
+            session.undo.push(step);
+
+            ControlFlow::Continue(())
+        },
+        MoveOutcome::Stale(current) => {
+            event!(Level::TRACE, client_id, %address, id, "stale move, correcting");
+
+            send_message(
+                socket,
+                &ServerMessage::Correction(current),
+                session.codec,
+                client_id,
+                address,
+            )
+            .await
+        },
+        MoveOutcome::Grabbed(current) => {
+            event!(Level::TRACE, client_id, %address, id, "move of a word grabbed by someone else, correcting");
+
+            send_message(
+                socket,
+                &ServerMessage::Correction(current),
+                session.codec,
+                client_id,
+                address,
+            )
+            .await
+        },
+        // the fridge may have shrunk while the move was on its way, put the word back
+        MoveOutcome::OutOfBounds => match state.word(id).await {
+            Some(word) => {
+                event!(Level::DEBUG, client_id, %address, x, y, "out of bounds move, correcting");
+
+                send_message(
+                    socket,
+                    &ServerMessage::Correction(MoveEventParams::from(&word)),
+                    session.codec,
+                    client_id,
+                    address,
+                )
+                .await
+            },
+            None => ControlFlow::Continue(()),
+        },
+        MoveOutcome::UnknownWord => {
+            event!(Level::WARN, client_id, %address, id, "invalid word id, disconnecting");
+            ControlFlow::Break(())
+        },
+    }
+}
+
+async fn handle_inbound(
+    result: Option<Result<Message, axum::Error>>,
+    client_id: u64,
+    address: SocketAddr,
+    state: &WsState,
+    socket: &mut WebSocket,
+    session: &mut Session,
+) -> ControlFlow<()> {
+    match result {
+        Some(Ok(message @ (Message::Text(_) | Message::Binary(_)))) => {
+            match codec::decode::<ClientMessage>(&message) {
+                Ok(ClientMessage::Move(move_event)) => {
+                    return handle_move(move_event, client_id, address, state, socket, session)
+                        .await;
+                },
+                Ok(ClientMessage::Drag(drag)) => {
+                    let (id, x, y) = (drag.id, drag.x, drag.y);
+
+                    match state.drag(drag, client_id).await {
+                        DragOutcome::Recorded => {},
+                        DragOutcome::Grabbed => {
+                            event!(Level::TRACE, client_id, %address, id, "drag of a word grabbed by someone else, ignoring");
+                        },
+                        DragOutcome::OutOfBounds => {
+                            // the fridge may have shrunk while the drag was on its way
+                            event!(Level::TRACE, client_id, %address, x, y, "out of bounds drag, ignoring");
+                        },
+                        DragOutcome::UnknownWord => {
+                            event!(Level::WARN, client_id, %address, id, "invalid word id, disconnecting");
+                            return ControlFlow::Break(());
+                        },
+                    }
+                },
+                Ok(ClientMessage::Grab { id }) => {
+                    if !take_moves(state, session, 1, client_id, address)? {
+                        return ControlFlow::Continue(());
+                    }
+
+                    match state.grab(id, client_id).await {
+                        GrabOutcome::Grabbed { .. } => {},
+                        GrabOutcome::HeldByOther => {
+                            // let the poet know they can't have it
+                            return send_message(
+                                socket,
+                                &ServerMessage::Grabbed { id },
+                                session.codec,
+                                client_id,
+                                address,
+                            )
+                            .await;
+                        },
+                        GrabOutcome::UnknownWord => {
+                            event!(Level::WARN, client_id, %address, id, "invalid word id, disconnecting");
+                            return ControlFlow::Break(());
+                        },
+                    }
+                },
+                Ok(ClientMessage::Release { id }) => {
+                    // a dropped release is let go of when the grab times out
+                    if !take_moves(state, session, 1, client_id, address)? {
+                        return ControlFlow::Continue(());
+                    }
+
+                    state.release(id, client_id);
+                },
+                Ok(ClientMessage::Undo { count }) => {
+                    let count = undo_count(count, state.move_limits());
+
+                    if !take_moves(state, session, undo_cost(count), client_id, address)? {
+                        return ControlFlow::Continue(());
+                    }
+
+                    let undone = undo(state, &mut session.undo, client_id, count).await;
+
+                    event!(Level::TRACE, client_id, %address, count, undone, "undo");
+                },
+                Ok(ClientMessage::Redo { count }) => {
+                    let count = undo_count(count, state.move_limits());
+
+                    if !take_moves(state, session, undo_cost(count), client_id, address)? {
+                        return ControlFlow::Continue(());
+                    }
+
+                    let redone = redo(state, &mut session.undo, client_id, count).await;
+
+                    event!(Level::TRACE, client_id, %address, count, redone, "redo");
+                },
+                Ok(ClientMessage::Pong { .. }) => {
+                    session.liveness.pong();
+                },
+                Err(error) => {
+                    event!(Level::TRACE, ?error, client_id, %address, "invalid message received");
+                },
+            }
+
+            ControlFlow::Continue(())
+        },
+        Some(Ok(Message::Close(_))) | None => ControlFlow::Break(()),
+        Some(Err(error)) => {
+            event!(Level::TRACE, ?error, client_id, %address, "websocket read error");
+            ControlFlow::Break(())
+        },
+        Some(Ok(_)) => ControlFlow::Continue(()),
+    }
+}
+
+async fn handle_socket(
+    mut socket: WebSocket,
+    state: Arc<WsState>,
+    config: &Config,
+    address: SocketAddr,
+    codec: Codec,
+    resume: Option<Resume>,
+) {
+    let client_id = state
+        .next_client_id
+        .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+
+    event!(Level::DEBUG, client_id, %address, fridge = state.name, "Client connected");
+
+    // subscribe before sending the dimensions and the words, so that no resize or move falls in between
+    let (mut broadcast_rx, sequence, missed) = state.subscribe(resume.as_ref());
+
+    // send fridge dimensions
+    let dimensions = ServerMessage::from(state.fridge_dimensions());
+
+    if send_message(&mut socket, &dimensions, codec, client_id, address)
+        .await
+        .is_break()
+    {
+        return;
+    }
+
+    let flow = match missed {
+        Some(missed) => {
+            event!(Level::DEBUG, client_id, %address, missed = missed.len(), "Client resumed");
+
+            send_frames(&mut socket, &missed, codec, client_id, address).await
+        },
+        None => send_fridge(&mut socket, &state, codec, client_id, address).await,
+    };
+
+    if flow.is_break()
+        || send_message(&mut socket, &sequence, codec, client_id, address)
+            .await
+            .is_break()
+    {
+        return;
+    }
+
+    state.join();
+
+    let move_limits = state.move_limits();
+
+    let mut session = Session {
+        liveness: Liveness::new(config.pong_timeout),
+        undo: UndoStack::default(),
+        codec,
+        moves: TokenBucket::new(move_limits.poet),
+        dropped_moves: TokenBucket::new(dropped_moves_limit(move_limits)),
+    };
+
+    loop {
+        let flow = tokio::select! {
+            result = broadcast_rx.recv() => handle_outbound(result, &mut broadcast_rx, &state, client_id, address, &mut socket, &mut session).await,
+            result = socket.recv() => handle_inbound(result, client_id, address, &state, &mut socket, &mut session).await,
+        };
+
+        if flow.is_break() {
+            break;
+        }
+    }
+
+    // client disconnected, clean up
+    for id in state.grabs.release_all(client_id) {
+        state.broadcast(None, ServerMessage::Released { id });
+    }
+
+    state.leave();
+
+    event!(Level::TRACE, client_id, %address, fridge = state.name, "Client disconnected");
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+    use std::ops::ControlFlow;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use pretty_assertions::assert_eq;
+    use tokio::sync::broadcast;
+
+    use crate::states::config::{FridgeDimensions, MoveLimits, RateLimit};
+    use crate::words::codec;
+    use crate::words::grabs::GrabOutcome;
+    use crate::words::history::{self, Position};
+    use crate::words::liveness::Liveness;
+    use crate::words::rate_limit::TokenBucket;
+    use crate::words::snapshot::Snapshot;
+    use crate::words::undo::{MAX_UNDO, Step, UndoStack};
+    use crate::words::{
+        Broadcast, Codec, DragEventParams, DragOutcome, MoveEventParams, MoveOutcome,
+        ServerMessage, Session, WordInfo, WsState, build_ws_state, catch_up, dropped_moves_limit,
+        redo, take_moves, undo, undo_cost, undo_count,
+    };
+
+    const FRIDGE: FridgeDimensions = FridgeDimensions {
+        fridge_width: 100,
+        fridge_height: 100,
+    };
+
+    /// A fridge with a word at each of `positions`.
+    fn fridge(positions: &[(u32, u32)]) -> Arc<WsState> {
+        let words = (0..positions.len())
+            .map(|id| format!("word{}", id))
+            .collect::<Vec<_>>();
+
+        let snapshot = Snapshot {
+            fridge_dimensions: FRIDGE,
+            words: words
+                .iter()
+                .zip(positions)
+                .enumerate()
+                .map(|(id, (word, &(x, y)))| WordInfo {
+                    id,
+                    word: word.clone(),
+                    v: 0,
+                    x,
+                    y,
+                })
+                .collect(),
+        };
+
+        build_ws_state(
+            "test",
+            &words,
+            FRIDGE,
+            Some(snapshot),
+            100,
+            64,
+            MoveLimits {
+                poet: RateLimit {
+                    per_second: 1.0,
+                    burst: 5,
+                },
+                address: None,
+                max_dropped: 10,
+            },
+        )
+    }
+
+    fn move_event(id: usize, v: usize, x: u32, y: u32) -> MoveEventParams {
+        MoveEventParams { id, v, x, y }
+    }
+
+    /// Word `id`'s version and position.
+    async fn word(state: &WsState, id: usize) -> (usize, u32, u32) {
+        let word = state.word(id).await.unwrap();
+
+        (word.v, word.x, word.y)
+    }
+
+    /// The messages broadcast since `broadcast_rx` subscribed.
+    fn broadcasts(broadcast_rx: &mut broadcast::Receiver<Broadcast>) -> Vec<ServerMessage> {
+        std::iter::from_fn(|| broadcast_rx.try_recv().ok())
+            .map(|(_, frame)| frame.message().clone())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn moves_bump_the_version() {
+        let state = fridge(&[(10, 10)]);
+        let mut broadcast_rx = state.broadcast_tx.subscribe();
+
+        let MoveOutcome::Moved(step) = state.apply_move(move_event(0, 0, 20, 30), Some(1)).await
+        else {
+            panic!("a move against the current version is made");
+        };
+
+        assert_eq!(
+            step,
+            Step {
+                id: 0,
+                from: Position { x: 10, y: 10 },
+                to: Position { x: 20, y: 30 },
+            },
+            "the step to undo"
+        );
+        assert_eq!(word(&state, 0).await, (1, 20, 30), "moved, one version on");
+        assert_eq!(
+            broadcasts(&mut broadcast_rx),
+            [ServerMessage::Move(move_event(0, 1, 20, 30))],
+            "everyone is told about the new version"
+        );
+    }
+
+    #[tokio::test]
+    async fn stale_moves_are_corrected() {
+        let state = fridge(&[(10, 10)]);
+
+        assert!(
+            matches!(
+                state.apply_move(move_event(0, 0, 20, 20), Some(1)).await,
+                MoveOutcome::Moved(_)
+            ),
+            "the first move against version 0"
+        );
+
+        let MoveOutcome::Stale(current) = state.apply_move(move_event(0, 0, 50, 50), Some(2)).await
+        else {
+            panic!("the second move against version 0 is stale");
+        };
+
+        assert_eq!(current, move_event(0, 1, 20, 20), "where the word is");
+        assert_eq!(word(&state, 0).await, (1, 20, 20), "left where it was");
+    }
+
+    #[tokio::test]
+    async fn words_grabbed_by_others_cant_be_moved() {
+        let state = fridge(&[(10, 10)]);
+
+        assert_eq!(
+            state.grab(0, 1).await,
+            GrabOutcome::Grabbed { released: None },
+            "grabbed"
+        );
+
+        let MoveOutcome::Grabbed(current) =
+            state.apply_move(move_event(0, 0, 50, 50), Some(2)).await
+        else {
+            panic!("someone else holds the word");
+        };
+
+        assert_eq!(current, move_event(0, 0, 10, 10), "where the word is");
+        assert!(
+            matches!(
+                state.apply_move(move_event(0, 0, 50, 50), Some(1)).await,
+                MoveOutcome::Moved(_)
+            ),
+            "the poet holding it moves it"
+        );
+        assert!(
+            !state.grabs.is_held_by_other(0, Some(2)),
+            "dropping the word lets go of it"
+        );
+    }
+
+    #[tokio::test]
+    async fn out_of_bounds_moves_are_refused() {
+        let state = fridge(&[(10, 10)]);
+
+        for (x, y) in [(101, 50), (50, 101)] {
+            assert!(
+                matches!(
+                    state.apply_move(move_event(0, 0, x, y), Some(1)).await,
+                    MoveOutcome::OutOfBounds
+                ),
+                "({}, {}) is off the fridge",
+                x,
+                y
+            );
+        }
+
+        assert_eq!(word(&state, 0).await, (0, 10, 10), "left where it was");
+        assert!(
+            matches!(
+                state.apply_move(move_event(0, 0, 100, 100), Some(1)).await,
+                MoveOutcome::Moved(_)
+            ),
+            "the edge is on the fridge"
+        );
+    }
+
+    #[tokio::test]
+    async fn words_are_added_after_those_on_the_fridge() {
+        let state = fridge(&[(10, 10), (20, 20)]);
+        let mut broadcast_rx = state.broadcast_tx.subscribe();
+
+        let words = ["word0", "word1", "word2"].map(str::to_owned);
+
+        assert_eq!(state.add_words(&words).await, Some(1), "one new word");
+
+        let added = state.word(2).await.unwrap();
+
+        assert_eq!(added.word, "word2", "after those on the fridge");
+        assert_eq!(
+            broadcasts(&mut broadcast_rx),
+            [ServerMessage::Added(vec![added])],
+            "everyone is told about the new word"
+        );
+        assert_eq!(word(&state, 0).await, (0, 10, 10), "the others stay put");
+        assert_eq!(state.add_words(&words).await, Some(0), "nothing new");
+    }
+
+    #[tokio::test]
+    async fn words_cant_be_taken_away_or_changed() {
+        let state = fridge(&[(10, 10), (20, 20)]);
+        let mut broadcast_rx = state.broadcast_tx.subscribe();
+
+        assert_eq!(
+            state.add_words(&["word0".to_owned()]).await,
+            None,
+            "taken away"
+        );
+        assert_eq!(
+            state
+                .add_words(&["word0", "other", "word2"].map(str::to_owned))
+                .await,
+            None,
+            "changed"
+        );
+        assert_eq!(state.words().await.len(), 2, "left as they were");
+        assert_eq!(broadcasts(&mut broadcast_rx), [], "nobody is told anything");
+    }
+
+    #[tokio::test]
+    async fn shrinking_moves_words_onto_the_fridge() {
+        let state = fridge(&[(10, 10), (90, 20), (30, 95)]);
+        let mut broadcast_rx = state.broadcast_tx.subscribe();
+
+        let moved = state
+            .set_fridge_dimensions(FridgeDimensions {
+                fridge_width: 50,
+                fridge_height: 80,
+            })
+            .await;
+
+        assert_eq!(moved, 2, "two were off the fridge");
+        assert_eq!(
+            broadcasts(&mut broadcast_rx),
+            [
+                ServerMessage::Config {
+                    fridge_width: 50,
+                    fridge_height: 80,
+                },
+                ServerMessage::Move(move_event(1, 1, 50, 20)),
+                ServerMessage::Move(move_event(2, 1, 30, 80)),
+            ],
+            "everyone is told about the new size, and where the words went"
+        );
+        assert_eq!(word(&state, 0).await, (0, 10, 10), "on the fridge, stays");
+        assert_eq!(
+            state
+                .history_between(0, u64::MAX)
+                .iter()
+                .map(|entry| (entry.id, entry.from, entry.to, entry.client_id))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    1,
+                    Position { x: 90, y: 20 },
+                    Position { x: 50, y: 20 },
+                    None
+                ),
+                (
+                    2,
+                    Position { x: 30, y: 95 },
+                    Position { x: 30, y: 80 },
+                    None
+                ),
+            ],
+            "recorded, for a revert"
+        );
+    }
+
+    /// Moves word `id` at version `v` for `client_id`, and remembers it for them to undo.
+    async fn move_and_push(
+        state: &WsState,
+        stack: &mut UndoStack,
+        client_id: u64,
+        (id, v, x, y): (usize, usize, u32, u32),
+    ) {
+        let MoveOutcome::Moved(step) = state
+            .apply_move(move_event(id, v, x, y), Some(client_id))
+            .await
+        else {
+            panic!("word {} is at version {}", id, v);
+        };
+
+        stack.push(step);
+    }
+
+    #[tokio::test]
+    async fn undo_skips_words_moved_by_someone_else() {
+        let state = fridge(&[(10, 10), (20, 20)]);
+        let mut stack = UndoStack::default();
+
+        move_and_push(&state, &mut stack, 1, (0, 0, 15, 15)).await;
+        move_and_push(&state, &mut stack, 1, (1, 0, 25, 25)).await;
+
+        assert!(
+            matches!(
+                state.apply_move(move_event(1, 1, 40, 40), Some(2)).await,
+                MoveOutcome::Moved(_)
+            ),
+            "someone else moves word 1"
+        );
+        assert_eq!(
+            undo(&state, &mut stack, 1, 2).await,
+            1,
+            "only word 0 is undone"
+        );
+        assert_eq!(word(&state, 0).await, (2, 10, 10), "back where it was");
+        assert_eq!(
+            word(&state, 1).await,
+            (2, 40, 40),
+            "where the other poet put it"
+        );
+        assert_eq!(
+            redo(&state, &mut stack, 1, 2).await,
+            1,
+            "only the undone move is redone"
+        );
+        assert_eq!(word(&state, 0).await, (3, 15, 15), "redone");
+    }
+
+    #[tokio::test]
+    async fn new_moves_clear_what_can_be_redone() {
+        let state = fridge(&[(10, 10)]);
+        let mut stack = UndoStack::default();
+
+        move_and_push(&state, &mut stack, 1, (0, 0, 20, 20)).await;
+
+        assert_eq!(undo(&state, &mut stack, 1, 1).await, 1, "undone");
+        assert_eq!(word(&state, 0).await, (2, 10, 10), "back where it was");
+
+        move_and_push(&state, &mut stack, 1, (0, 2, 30, 30)).await;
+
+        assert_eq!(
+            redo(&state, &mut stack, 1, 1).await,
+            0,
+            "nothing to redo after a new move"
+        );
+        assert_eq!(
+            word(&state, 0).await,
+            (3, 30, 30),
+            "where the new move put it"
+        );
+    }
+
+    #[tokio::test]
+    async fn revert_puts_every_word_back() {
+        let state = fridge(&[(10, 10), (20, 20), (30, 30)]);
+
+        // the moves are made after this, even within the same millisecond
+        let at = history::now().saturating_sub(1);
+
+        for (id, v, x, client_id) in [(0, 0, 50, Some(1)), (0, 1, 60, Some(2)), (1, 0, 70, None)] {
+            assert!(
+                matches!(
+                    state.apply_move(move_event(id, v, x, x), client_id).await,
+                    MoveOutcome::Moved(_)
+                ),
+                "word {} moved to {}",
+                id,
+                x
+            );
+        }
+
+        let mut broadcast_rx = state.broadcast_tx.subscribe();
+
+        let report = state.revert_to(at).await;
+
+        assert_eq!(
+            (report.moved, report.complete),
+            (2, true),
+            "both words that moved"
+        );
+        assert_eq!(
+            [
+                word(&state, 0).await,
+                word(&state, 1).await,
+                word(&state, 2).await
+            ],
+            [(3, 10, 10), (2, 20, 20), (0, 30, 30)],
+            "back where they were, the word that didn't move untouched"
+        );
+        assert_eq!(
+            broadcasts(&mut broadcast_rx),
+            [
+                ServerMessage::Move(move_event(0, 3, 10, 10)),
+                ServerMessage::Move(move_event(1, 2, 20, 20)),
+            ],
+            "everyone is told"
+        );
+        assert_eq!(
+            state
+                .history_between(0, u64::MAX)
+                .iter()
+                .skip(3)
+                .map(|entry| (entry.id, entry.from, entry.to, entry.client_id))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    0,
+                    Position { x: 60, y: 60 },
+                    Position { x: 10, y: 10 },
+                    None
+                ),
+                (
+                    1,
+                    Position { x: 70, y: 70 },
+                    Position { x: 20, y: 20 },
+                    None
+                ),
+            ],
+            "the revert is recorded as moves of its own"
+        );
+    }
+
+    #[tokio::test]
+    async fn drags_are_sent_once_per_word() {
+        let state = fridge(&[(10, 10), (20, 20)]);
+        let mut broadcast_rx = state.broadcast_tx.subscribe();
+
+        for (id, x) in [(0, 11), (0, 12), (1, 21), (0, 13)] {
+            assert!(
+                matches!(
+                    state.drag(DragEventParams { id, x, y: x }, 1).await,
+                    DragOutcome::Recorded
+                ),
+                "word {} dragged to {}",
+                id,
+                x
+            );
+        }
+
+        state.flush_drags();
+
+        let mut sent = broadcasts(&mut broadcast_rx)
+            .into_iter()
+            .map(|message| {
+                let ServerMessage::Drag(drag) = message else {
+                    panic!("only drags are sent");
+                };
+
+                (drag.id, drag.x, drag.y)
+            })
+            .collect::<Vec<_>>();
+        sent.sort_unstable();
+
+        assert_eq!(
+            sent,
+            [(0, 13, 13), (1, 21, 21)],
+            "the latest position of each"
+        );
+
+        state.flush_drags();
+
+        assert_eq!(broadcasts(&mut broadcast_rx), [], "nothing left to send");
+    }
+
+    #[test]
+    fn undos_are_limited_to_what_poets_can_afford() {
+        let mut limits = MoveLimits {
+            poet: RateLimit {
+                per_second: 1.0,
+                burst: 5,
+            },
+            address: None,
+            max_dropped: 10,
+        };
+
+        assert_eq!(undo_count(3, limits), 3, "affordable");
+        assert_eq!(undo_count(100, limits), 5, "the poet's burst");
+
+        limits.address = Some(RateLimit {
+            per_second: 1.0,
+            burst: 4,
+        });
+
+        assert_eq!(undo_count(100, limits), 4, "the address' burst");
+
+        limits.poet.burst = u32::MAX;
+        limits.address = None;
+
+        assert_eq!(
+            undo_count(usize::MAX, limits),
+            MAX_UNDO,
+            "no more than MAX_UNDO"
+        );
+        assert_eq!(undo_cost(0), 1, "undoing nothing still costs a move");
+        assert_eq!(undo_cost(4), 4, "a move per move undone");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn undos_are_counted_against_the_rate_limit() {
+        let state = fridge(&[(10, 10)]);
+        let move_limits = MoveLimits {
+            poet: RateLimit {
+                per_second: 1.0,
+                burst: 5,
+            },
+            address: Some(RateLimit {
+                per_second: 1.0,
+                burst: 6,
+            }),
+            max_dropped: 1,
+        };
+        state.set_move_limits(move_limits);
+
+        let mut session = Session {
+            liveness: Liveness::new(Duration::from_secs(10)),
+            undo: UndoStack::default(),
+            codec: Codec::Json,
+            moves: TokenBucket::new(move_limits.poet),
+            dropped_moves: TokenBucket::new(dropped_moves_limit(move_limits)),
+        };
+        let address = SocketAddr::from(([127, 0, 0, 1], 1234));
+        let cost = undo_cost(undo_count(100, move_limits));
+
+        assert_eq!(
+            take_moves(&state, &mut session, cost, 1, address),
+            ControlFlow::Continue(true),
+            "a full undo is affordable"
+        );
+        assert_eq!(
+            take_moves(&state, &mut session, 1, 1, address),
+            ControlFlow::Continue(false),
+            "but leaves no moves"
+        );
+        assert_eq!(
+            take_moves(&state, &mut session, 1, 1, address),
+            ControlFlow::Break(()),
+            "too many dropped"
+        );
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+
+        let mut session = Session {
+            moves: TokenBucket::new(move_limits.poet),
+            dropped_moves: TokenBucket::new(dropped_moves_limit(move_limits)),
+            ..session
+        };
+
+        assert_eq!(
+            take_moves(&state, &mut session, cost, 2, address),
+            ControlFlow::Continue(false),
+            "the undo came from the same address, which has 2 moves left"
+        );
+        assert!(
+            session.moves.can_take(cost),
+            "the poet's moves aren't lost to the address' limit"
+        );
+    }
+
+    #[tokio::test]
+    async fn poets_who_fall_behind_get_a_fresh_copy_of_the_fridge() {
+        let state = fridge(&[(10, 10), (20, 20)]);
+        let mut broadcast_rx = state.broadcast_tx.subscribe();
+
+        assert!(
+            matches!(state.grab(1, 2).await, GrabOutcome::Grabbed { .. }),
+            "grabbed"
+        );
+
+        let Some(messages) = catch_up(&state, &mut broadcast_rx, Codec::Json).await else {
+            panic!("everything encodes");
+        };
+
+        assert_eq!(
+            messages
+                .iter()
+                .map(|message| {
+                    let message = codec::decode::<serde_json::Value>(message).unwrap();
+
+                    message["type"].as_str().unwrap().to_owned()
+                })
+                .collect::<Vec<_>>(),
+            ["config", "words", "grabbed", "sequence", "poets"],
+            "the dimensions, the words and those being dragged, and where to resume from"
+        );
+        assert_eq!(
+            broadcasts(&mut broadcast_rx),
+            [],
+            "the backlog the copy covers is skipped"
+        );
+    }
 }
@@ -0,0 +1,207 @@
+use axum::extract::ws::Message;
+use axum::http::HeaderValue;
+use color_eyre::eyre;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+pub const JSON_PROTOCOL: &str = "magwords.json";
+pub const MESSAGE_PACK_PROTOCOL: &str = "magwords.msgpack";
+
+/// The `Sec-WebSocket-Protocol`s we speak, most preferred first.
+pub const SUBPROTOCOLS: [&str; 2] = [MESSAGE_PACK_PROTOCOL, JSON_PROTOCOL];
+
+/// How messages to a poet are encoded, as negotiated through `Sec-WebSocket-Protocol`.
+/// Poets that don't ask for a subprotocol get JSON.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Codec {
+    #[default]
+    Json,
+    /// `MessagePack`, with structs as maps, so that they look like their JSON counterparts.
+    MessagePack,
+}
+
+impl Codec {
+    pub fn from_protocol(protocol: Option<&HeaderValue>) -> Self {
+        match protocol.map(HeaderValue::as_bytes) {
+            Some(protocol) if protocol == MESSAGE_PACK_PROTOCOL.as_bytes() => Codec::MessagePack,
+            Some(_) | None => Codec::Json,
+        }
+    }
+
+    /// Encodes `value` as a text message for JSON, or as a binary one for `MessagePack`.
+    ///
+    /// # Errors
+    /// * `value` can't be serialized
+    pub fn encode<T>(self, value: &T) -> Result<Message, eyre::Report>
+    where
+        T: Serialize + ?Sized,
+    {
+        match self {
+            Codec::Json => Ok(Message::text(serde_json::to_string(value)?)),
+            Codec::MessagePack => Ok(Message::binary(rmp_serde::to_vec_named(value)?)),
+        }
+    }
+}
+
+/// Decodes a text message as JSON, and a binary one as `MessagePack`, whatever the poet negotiated.
+///
+/// # Errors
+/// * The message doesn't hold a `T`
+/// * The message is neither text nor binary, such as a ping
+pub fn decode<T>(message: &Message) -> Result<T, eyre::Report>
+where
+    T: DeserializeOwned,
+{
+    match *message {
+        Message::Text(ref text) => Ok(serde_json::from_str(text)?),
+        Message::Binary(ref bytes) => Ok(rmp_serde::from_slice(bytes)?),
+        Message::Ping(_) | Message::Pong(_) | Message::Close(_) => {
+            Err(eyre::Report::msg("not a text or binary message"))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Debug;
+
+    use axum::extract::ws::Message;
+    use axum::http::HeaderValue;
+    use pretty_assertions::assert_eq;
+    use serde::de::DeserializeOwned;
+    use serde::{Deserialize, Serialize};
+
+    use crate::words::codec::{Codec, decode};
+    use crate::words::{
+        ClientMessage, DragEventParams, MoveEventParams, ServerMessage, WireWords, WordInfo,
+    };
+
+    fn round_trip<T>(value: &T)
+    where
+        T: Serialize + DeserializeOwned + PartialEq + Debug,
+    {
+        for codec in [Codec::Json, Codec::MessagePack] {
+            let message = codec.encode(value).unwrap();
+
+            let decoded = decode::<T>(&message).unwrap();
+
+            assert_eq!(&decoded, value, "{:?}", codec);
+        }
+    }
+
+    fn move_event() -> MoveEventParams {
+        MoveEventParams {
+            id: 3,
+            v: 7,
+            x: 10,
+            y: 20,
+        }
+    }
+
+    #[test]
+    fn server_messages_round_trip() {
+        for message in [
+            ServerMessage::Config {
+                fridge_width: 990,
+                fridge_height: 1600,
+            },
+            ServerMessage::Poets { count: 4 },
+            ServerMessage::Move(move_event()),
+            ServerMessage::Drag(DragEventParams { id: 3, x: 1, y: 2 }),
+            ServerMessage::Correction(move_event()),
+            ServerMessage::Grabbed { id: 3 },
+            ServerMessage::Released { id: 3 },
+            ServerMessage::Added(vec![WordInfo {
+                id: 4,
+                word: "magnet".to_owned(),
+                v: 0,
+                x: 5,
+                y: 6,
+            }]),
+            ServerMessage::Hup { id: 1, v: 2 },
+            ServerMessage::Goodbye {},
+            ServerMessage::Sequence {
+                stream: "0192b3c4-d5e6-7f80-9a1b-2c3d4e5f6a7b".to_owned(),
+                seq: 42,
+            },
+        ] {
+            round_trip(&message);
+        }
+    }
+
+    #[test]
+    fn client_messages_round_trip() {
+        for message in [
+            ClientMessage::Move(move_event()),
+            ClientMessage::Drag(DragEventParams { id: 3, x: 1, y: 2 }),
+            ClientMessage::Grab { id: 3 },
+            ClientMessage::Release { id: 3 },
+            ClientMessage::Undo { count: 2 },
+            ClientMessage::Redo { count: 1 },
+            ClientMessage::Pong { id: 1 },
+        ] {
+            round_trip(&message);
+        }
+    }
+
+    #[test]
+    fn words_round_trip() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Words {
+            r#type: String,
+            data: Vec<WordInfo>,
+        }
+
+        let words = [WordInfo {
+            id: 0,
+            word: "fridge".to_owned(),
+            v: 1,
+            x: 2,
+            y: 3,
+        }];
+
+        for codec in [Codec::Json, Codec::MessagePack] {
+            let message = codec
+                .encode(&WireWords {
+                    r#type: "words",
+                    data: &words,
+                })
+                .unwrap();
+
+            let decoded = decode::<Words>(&message).unwrap();
+
+            assert_eq!(decoded.r#type, "words", "{:?}", codec);
+            assert_eq!(decoded.data, words, "{:?}", codec);
+        }
+    }
+
+    #[test]
+    fn json_is_text_and_message_pack_is_binary() {
+        let message = ServerMessage::Poets { count: 1 };
+
+        assert!(
+            matches!(Codec::Json.encode(&message).unwrap(), Message::Text(_)),
+            "JSON is text"
+        );
+        assert!(
+            matches!(
+                Codec::MessagePack.encode(&message).unwrap(),
+                Message::Binary(_)
+            ),
+            "MessagePack is binary"
+        );
+    }
+
+    #[test]
+    fn negotiates_subprotocols() {
+        assert_eq!(Codec::from_protocol(None), Codec::Json);
+        assert_eq!(
+            Codec::from_protocol(Some(&HeaderValue::from_static("magwords.json"))),
+            Codec::Json
+        );
+        assert_eq!(
+            Codec::from_protocol(Some(&HeaderValue::from_static("magwords.msgpack"))),
+            Codec::MessagePack
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use axum::extract::connect_info::ConnectInfo;
+use axum::extract::{FromRef, FromRequestParts};
+use axum::http::request::Parts;
+use hashbrown::HashMap;
+
+use crate::states::config::Config;
+use crate::utils::forwarded::client_address;
+
+/// The websockets open at the moment, across all fridges, and per address.
+pub struct Connections {
+    max_total: AtomicUsize,
+    max_per_address: AtomicUsize,
+    counts: Mutex<Counts>,
+}
+
+#[derive(Default)]
+struct Counts {
+    total: usize,
+    per_address: HashMap<IpAddr, usize>,
+}
+
+/// Why a websocket was refused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectionLimit {
+    TooManyPoets,
+    TooManyFromAddress,
+}
+
+impl ConnectionLimit {
+    /// Sent along in the close frame.
+    pub fn reason(self) -> &'static str {
+        match self {
+            ConnectionLimit::TooManyPoets => "too many poets, try again later",
+            ConnectionLimit::TooManyFromAddress => "too many connections from your address",
+        }
+    }
+}
+
+impl Connections {
+    pub fn new(max_total: usize, max_per_address: usize) -> Self {
+        Self {
+            max_total: AtomicUsize::new(max_total),
+            max_per_address: AtomicUsize::new(max_per_address),
+            counts: Mutex::new(Counts::default()),
+        }
+    }
+
+    /// Changes the limits, for connections opened from now on. Those open already stay open.
+    pub fn set_limits(&self, max_total: usize, max_per_address: usize) {
+        self.max_total.store(max_total, Ordering::Relaxed);
+        self.max_per_address
+            .store(max_per_address, Ordering::Relaxed);
+    }
+
+    /// Counts a connection from `ip`, until the returned [`Connection`] is dropped.
+    ///
+    /// # Errors
+    /// * There are as many connections as we allow, in total or from `ip`
+    pub fn open(self: &Arc<Self>, ip: IpAddr) -> Result<Connection, ConnectionLimit> {
+        let mut counts = self.counts.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if counts.total >= self.max_total.load(Ordering::Relaxed) {
+            return Err(ConnectionLimit::TooManyPoets);
+        }
+
+        if counts.per_address.get(&ip).copied().unwrap_or(0)
+            >= self.max_per_address.load(Ordering::Relaxed)
+        {
+            return Err(ConnectionLimit::TooManyFromAddress);
+        }
+
+        *counts.per_address.entry(ip).or_insert(0) += 1;
+        counts.total += 1;
+
+        Ok(Connection {
+            connections: Arc::clone(self),
+            ip,
+        })
+    }
+
+    fn close(&self, ip: IpAddr) {
+        let mut counts = self.counts.lock().unwrap_or_else(PoisonError::into_inner);
+
+        counts.total -= 1;
+
+        if let Some(from_address) = counts.per_address.get_mut(&ip) {
+            *from_address -= 1;
+
+            if *from_address == 0 {
+                counts.per_address.remove(&ip);
+            }
+        }
+    }
+}
+
+/// An open websocket, counted towards the limits until dropped.
+pub struct Connection {
+    connections: Arc<Connections>,
+    ip: IpAddr,
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.connections.close(self.ip);
+    }
+}
+
+/// Where a request comes from, which is the poet behind a proxy only when we trust the proxy.
+pub struct ClientAddress(pub SocketAddr);
+
+impl<S> FromRequestParts<S> for ClientAddress
+where
+    Arc<Config>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = <ConnectInfo<SocketAddr> as FromRequestParts<S>>::Rejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let ConnectInfo(peer) = ConnectInfo::<SocketAddr>::from_request_parts(parts, state).await?;
+
+        let config = Arc::<Config>::from_ref(state);
+
+        Ok(Self(client_address(
+            peer,
+            &parts.headers,
+            &config.trusted_proxies,
+        )))
+    }
+}
+
+/// Whether someone who asks for a websocket, or an SSE stream, may have one.
+pub struct Admission {
+    /// The poet's address, which is that of the proxy in between only when we don't trust it.
+    pub address: SocketAddr,
+    pub connection: Result<Connection, ConnectionLimit>,
+}
+
+impl<S> FromRequestParts<S> for Admission
+where
+    Arc<Config>: FromRef<S>,
+    Arc<Connections>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = <ConnectInfo<SocketAddr> as FromRequestParts<S>>::Rejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let ClientAddress(address) = ClientAddress::from_request_parts(parts, state).await?;
+
+        Ok(Self {
+            address,
+            connection: Arc::<Connections>::from_ref(state).open(address.ip()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+    use std::sync::Arc;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::words::connections::{ConnectionLimit, Connections};
+
+    #[test]
+    fn limits_connections() {
+        let connections = Arc::new(Connections::new(3, 2));
+
+        let (first, second): (IpAddr, IpAddr) =
+            ("192.0.2.1".parse().unwrap(), "192.0.2.2".parse().unwrap());
+
+        let a = connections.open(first).unwrap();
+        let _b = connections.open(first).unwrap();
+
+        assert_eq!(
+            connections.open(first).err(),
+            Some(ConnectionLimit::TooManyFromAddress),
+            "too many from the first address"
+        );
+
+        let _c = connections.open(second).unwrap();
+
+        assert_eq!(
+            connections.open(second).err(),
+            Some(ConnectionLimit::TooManyPoets),
+            "too many in total"
+        );
+
+        drop(a);
+
+        assert_eq!(
+            connections.open(first).err(),
+            None,
+            "room for the first address again"
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use hashbrown::HashMap;
+
+use crate::words::DragEventParams;
+
+/// How often dragged words' positions are sent out, 20 times a second.
+pub const DRAG_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The latest position of every word being dragged, waiting to be sent out.
+///
+/// Poets send far more positions than anyone needs to see, so only the last one
+/// per word is kept, and sent out every [`DRAG_INTERVAL`].
+#[derive(Default)]
+pub struct Drags {
+    pending: Mutex<HashMap<usize, (u64, DragEventParams)>>,
+}
+
+impl Drags {
+    /// Records where `client_id` is dragging a word, replacing the position not yet sent out.
+    pub fn record(&self, client_id: u64, drag: DragEventParams) {
+        self.pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(drag.id, (client_id, drag));
+    }
+
+    /// Forgets the pending position of word `id`, as it has been dropped.
+    pub fn discard(&self, id: usize) {
+        self.pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&id);
+    }
+
+    /// Hands every pending position to `send`, along with the client dragging the word.
+    ///
+    /// `send` is called with the lock held, so that a position can't overtake the move that
+    /// dropped the word.
+    pub fn flush<F>(&self, send: F)
+    where
+        F: Fn(u64, DragEventParams),
+    {
+        let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+
+        for (_, (client_id, drag)) in pending.drain() {
+            send(client_id, drag);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::words::DragEventParams;
+    use crate::words::drags::Drags;
+
+    fn flushed(drags: &Drags) -> Vec<(u64, usize, u32, u32)> {
+        let sent = Mutex::new(Vec::new());
+
+        drags.flush(|client_id, drag| {
+            sent.lock()
+                .unwrap()
+                .push((client_id, drag.id, drag.x, drag.y));
+        });
+
+        let mut sent = sent.into_inner().unwrap();
+        sent.sort_unstable();
+
+        sent
+    }
+
+    #[test]
+    fn keeps_the_latest_position_per_word() {
+        let drags = Drags::default();
+
+        drags.record(10, DragEventParams { id: 1, x: 1, y: 1 });
+        drags.record(10, DragEventParams { id: 1, x: 2, y: 2 });
+        drags.record(11, DragEventParams { id: 2, x: 3, y: 3 });
+
+        assert_eq!(flushed(&drags), [(10, 1, 2, 2), (11, 2, 3, 3)]);
+        assert_eq!(flushed(&drags), [], "flushing empties");
+    }
+
+    #[test]
+    fn dropped_words_are_not_sent() {
+        let drags = Drags::default();
+
+        drags.record(10, DragEventParams { id: 1, x: 1, y: 1 });
+        drags.discard(1);
+
+        assert_eq!(flushed(&drags), []);
+    }
+}
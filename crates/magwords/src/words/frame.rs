@@ -0,0 +1,174 @@
+use std::sync::OnceLock;
+
+use axum::extract::ws::Message;
+use color_eyre::eyre;
+use serde::Serialize;
+use tracing::{Level, event};
+
+use crate::words::ServerMessage;
+use crate::words::codec::Codec;
+
+/// A broadcast message, encoded at most once per codec, however many poets it goes to.
+///
+/// Encoded messages share their buffer, so handing one to a poet is a reference count bump.
+#[derive(Debug)]
+pub struct Frame {
+    message: ServerMessage,
+    /// Set on broadcasts a reconnecting poet can resume from, see [`crate::words::recent::Recent`].
+    seq: Option<u64>,
+    json: OnceLock<Option<Message>>,
+    message_pack: OnceLock<Option<Message>>,
+}
+
+impl Frame {
+    pub fn new(message: ServerMessage) -> Self {
+        Self {
+            message,
+            seq: None,
+            json: OnceLock::new(),
+            message_pack: OnceLock::new(),
+        }
+    }
+
+    pub fn sequenced(message: ServerMessage, seq: u64) -> Self {
+        Self {
+            seq: Some(seq),
+            ..Self::new(message)
+        }
+    }
+
+    pub fn message(&self) -> &ServerMessage {
+        &self.message
+    }
+
+    pub fn seq(&self) -> Option<u64> {
+        self.seq
+    }
+
+    /// The message as `codec` encodes it, encoded by whoever asks first.
+    ///
+    /// `None` when the message can't be encoded, which is a bug, and logged once.
+    pub fn encoded(&self, codec: Codec) -> Option<Message> {
+        let encoded = match codec {
+            Codec::Json => &self.json,
+            Codec::MessagePack => &self.message_pack,
+        };
+
+        encoded
+            .get_or_init(|| match self.encode(codec) {
+                Ok(message) => Some(message),
+                Err(error) => {
+                    event!(
+                        Level::ERROR,
+                        ?error,
+                        message = ?self.message,
+                        ?codec,
+                        "failed to serialize message, this is a bug"
+                    );
+
+                    None
+                },
+            })
+            .clone()
+    }
+
+    fn encode(&self, codec: Codec) -> Result<Message, eyre::Report> {
+        match self.seq {
+            Some(seq) => codec.encode(&Sequenced {
+                message: &self.message,
+                seq,
+            }),
+            None => codec.encode(&self.message),
+        }
+    }
+}
+
+/// A message with its sequence number next to its `type` and `data`.
+#[derive(Serialize)]
+struct Sequenced<'a> {
+    #[serde(flatten)]
+    message: &'a ServerMessage,
+    seq: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::extract::ws::Message;
+    use pretty_assertions::assert_eq;
+    use serde::Deserialize;
+
+    use crate::words::codec::{Codec, decode};
+    use crate::words::frame::Frame;
+    use crate::words::{MoveEventParams, ServerMessage};
+
+    fn move_message() -> ServerMessage {
+        ServerMessage::Move(MoveEventParams {
+            id: 3,
+            v: 7,
+            x: 10,
+            y: 20,
+        })
+    }
+
+    #[test]
+    fn encodes_once_per_codec() {
+        let frame = Frame::new(move_message());
+
+        for codec in [Codec::Json, Codec::MessagePack] {
+            let first = frame.encoded(codec).unwrap();
+            let second = frame.encoded(codec).unwrap();
+
+            let same_buffer = match (&first, &second) {
+                (&Message::Text(ref first), &Message::Text(ref second)) => {
+                    first.as_str().as_ptr() == second.as_str().as_ptr()
+                },
+                (&Message::Binary(ref first), &Message::Binary(ref second)) => {
+                    first.as_ptr() == second.as_ptr()
+                },
+                _ => false,
+            };
+
+            assert!(same_buffer, "{:?} is encoded once", codec);
+            assert_eq!(
+                decode::<ServerMessage>(&first).unwrap(),
+                move_message(),
+                "{:?}",
+                codec
+            );
+        }
+    }
+
+    #[test]
+    fn numbers_next_to_type_and_data() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Sequenced {
+            #[serde(flatten)]
+            message: ServerMessage,
+            seq: u64,
+        }
+
+        let frame = Frame::sequenced(move_message(), 5);
+
+        for codec in [Codec::Json, Codec::MessagePack] {
+            let decoded = decode::<Sequenced>(&frame.encoded(codec).unwrap()).unwrap();
+
+            assert_eq!(
+                decoded,
+                Sequenced {
+                    message: move_message(),
+                    seq: 5,
+                },
+                "{:?}",
+                codec
+            );
+        }
+
+        assert!(
+            matches!(
+                frame.encoded(Codec::Json),
+                Some(Message::Text(json)) if json.as_str() == r#"{"type":"move","data":{"id":3,"v":7,"x":10,"y":20},"seq":5}"#
+            ),
+            "flat JSON"
+        );
+    }
+}
@@ -0,0 +1,661 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use color_eyre::eyre::{self, Context as _};
+use hashbrown::HashMap;
+use tokio::sync::{Mutex, MutexGuard};
+use tracing::{Level, event};
+
+use crate::states::config::{Config, FridgeDimensions, FridgePacks, MoveLimits};
+use crate::words::frame::Frame;
+use crate::words::snapshot;
+use crate::words::word_list::WordPacks;
+use crate::words::{ServerMessage, WsState, build_ws_state};
+
+/// The fridge poets end up on when they don't ask for a specific one.
+pub const DEFAULT_FRIDGE: &str = "default";
+
+const MAX_FRIDGE_NAME_LENGTH: usize = 64;
+
+#[derive(Debug)]
+pub enum FridgeError {
+    InvalidName,
+    TooManyFridges,
+    Snapshot(eyre::Report),
+}
+
+impl IntoResponse for FridgeError {
+    fn into_response(self) -> Response {
+        match self {
+            FridgeError::InvalidName => (
+                StatusCode::BAD_REQUEST,
+                "fridge names consist of 1 to 64 lowercase letters, digits, '-' or '_'",
+            )
+                .into_response(),
+            FridgeError::TooManyFridges => {
+                (StatusCode::SERVICE_UNAVAILABLE, "too many fridges").into_response()
+            },
+            FridgeError::Snapshot(error) => {
+                event!(Level::ERROR, ?error, "Failed to restore fridge");
+
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "failed to restore fridge",
+                )
+                    .into_response()
+            },
+        }
+    }
+}
+
+/// All the fridges served by this process, keyed by name.
+///
+/// Fridges other than the default one are created when the first poet walks up to them,
+/// and evicted (after being snapshotted) when nobody touched them for a while.
+pub struct Fridges {
+    default: Arc<WsState>,
+    named: Mutex<HashMap<String, Entry>>,
+    settings: RwLock<Arc<Settings>>,
+    snapshot_directory: Option<PathBuf>,
+    idle_timeout: Duration,
+    capacity: usize,
+    history_size: usize,
+    broadcast_capacity: usize,
+}
+
+/// A fridge other than the default one.
+enum Entry {
+    Ready(Arc<WsState>),
+    /// Being restored from its snapshot, or snapshotted before it's evicted, which is done without
+    /// holding the registry. Whoever does it holds the lock until the fridge is ready or gone.
+    Busy(Arc<Mutex<()>>),
+}
+
+impl Entry {
+    fn ready(&self) -> Option<&Arc<WsState>> {
+        match *self {
+            Entry::Ready(ref ws_state) => Some(ws_state),
+            Entry::Busy(_) => None,
+        }
+    }
+}
+
+/// What a reload changes, for the fridges there are and those created from then on.
+pub struct Settings {
+    /// Tiles for fridges without their own selection of packs.
+    tiles: Vec<String>,
+    fridge_tiles: HashMap<String, Vec<String>>,
+    fridge_dimensions: FridgeDimensions,
+    move_limits: MoveLimits,
+}
+
+impl Settings {
+    /// # Errors
+    /// * A fridge has an invalid name, or refers to a pack that doesn't exist
+    pub fn new(word_packs: &WordPacks, config: &Config) -> Result<Self, eyre::Report> {
+        let tiles = word_packs.tiles(&config.packs)?;
+
+        let mut fridge_tiles = HashMap::new();
+
+        for &FridgePacks {
+            ref fridge,
+            ref packs,
+        } in &config.fridge_packs
+        {
+            if !is_valid_fridge_name(fridge) {
+                return Err(eyre::Report::msg(format!(
+                    "`{}` is not a valid fridge name",
+                    fridge
+                )));
+            }
+
+            let tiles = word_packs
+                .tiles(packs)
+                .wrap_err_with(|| format!("Invalid word packs for fridge `{}`", fridge))?;
+
+            fridge_tiles.insert(fridge.clone(), tiles);
+        }
+
+        Ok(Self {
+            tiles,
+            fridge_tiles,
+            fridge_dimensions: config.fridge_dimensions,
+            move_limits: config.move_limits,
+        })
+    }
+
+    fn tiles(&self, fridge: &str) -> &[String] {
+        self.fridge_tiles.get(fridge).unwrap_or(&self.tiles)
+    }
+}
+
+impl Fridges {
+    /// Builds the registry, with the default fridge restored from its snapshot, if any.
+    ///
+    /// # Errors
+    /// * A fridge has an invalid name, or refers to a pack that doesn't exist
+    /// * The default fridge's snapshot couldn't be read
+    pub async fn new(word_packs: &WordPacks, config: &Config) -> Result<Self, eyre::Report> {
+        let settings = Settings::new(word_packs, config)?;
+        let snapshot_directory = config
+            .snapshot
+            .as_ref()
+            .map(|snapshot_config| snapshot_config.directory.clone());
+
+        let default = build_fridge(
+            DEFAULT_FRIDGE,
+            settings.tiles(DEFAULT_FRIDGE),
+            settings.fridge_dimensions,
+            snapshot_directory.as_deref(),
+            config.history_size,
+            config.broadcast_capacity,
+            settings.move_limits,
+        )
+        .await?;
+
+        Ok(Self {
+            default,
+            named: Mutex::new(HashMap::new()),
+            settings: RwLock::new(Arc::new(settings)),
+            snapshot_directory,
+            idle_timeout: config.fridge_idle_timeout,
+            capacity: config.max_fridges,
+            history_size: config.history_size,
+            broadcast_capacity: config.broadcast_capacity,
+        })
+    }
+
+    /// Applies the word lists, the fridge dimensions and the move limits of a reloaded configuration
+    /// to every fridge, without anyone having to reconnect.
+    ///
+    /// Words can only be added, after those on a fridge. A fridge whose words changed otherwise
+    /// keeps its words until we restart.
+    pub async fn reload(&self, settings: Settings) {
+        // hold the registry, so that no fridge is created with the old settings in the meantime,
+        // those being restored catch up when they're done, see `restore`
+        let named = self.named.lock().await;
+
+        for ws_state in
+            std::iter::once(&self.default).chain(named.values().filter_map(Entry::ready))
+        {
+            apply_settings(ws_state, &settings).await;
+        }
+
+        *self
+            .settings
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Arc::new(settings);
+    }
+
+    fn settings(&self) -> Arc<Settings> {
+        Arc::clone(&self.settings.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// The dimensions of every fridge.
+    pub fn fridge_dimensions(&self) -> FridgeDimensions {
+        self.settings().fridge_dimensions
+    }
+
+    pub fn default_fridge(&self) -> Arc<WsState> {
+        Arc::clone(&self.default)
+    }
+
+    /// Looks up a fridge, without creating it.
+    pub async fn get(&self, name: &str) -> Option<Arc<WsState>> {
+        if name == DEFAULT_FRIDGE {
+            return Some(self.default_fridge());
+        }
+
+        self.named
+            .lock()
+            .await
+            .get(name)
+            .and_then(Entry::ready)
+            .map(Arc::clone)
+    }
+
+    /// Looks up a fridge, creating (or restoring) it when it isn't there yet.
+    ///
+    /// # Errors
+    /// * The name isn't a valid fridge name
+    /// * We're at the maximum number of fridges
+    /// * The fridge's snapshot couldn't be read
+    pub async fn get_or_create(&self, name: &str) -> Result<Arc<WsState>, FridgeError> {
+        if name == DEFAULT_FRIDGE {
+            return Ok(self.default_fridge());
+        }
+
+        if !is_valid_fridge_name(name) {
+            return Err(FridgeError::InvalidName);
+        }
+
+        loop {
+            let busy = {
+                let fridges = self.named.lock().await;
+
+                match fridges.get(name) {
+                    Some(&Entry::Ready(ref ws_state)) => return Ok(Arc::clone(ws_state)),
+                    Some(&Entry::Busy(ref busy)) if busy.try_lock().is_err() => Arc::clone(busy),
+                    // nobody is busy with it, a restore was called off
+                    Some(&Entry::Busy(_)) => return self.restore(name, fridges).await,
+                    None => {
+                        // the default fridge counts too
+                        if fridges.len() + 1 >= self.capacity {
+                            return Err(FridgeError::TooManyFridges);
+                        }
+
+                        return self.restore(name, fridges).await;
+                    },
+                }
+            };
+
+            // restored or evicted in the meantime, look again once that's done
+            drop(busy.lock().await);
+        }
+    }
+
+    /// Creates fridge `name`, restoring it from its snapshot, without holding the registry while
+    /// reading it. The fridge is busy meanwhile, so that it's neither restored twice, nor while its
+    /// snapshot is written on eviction.
+    async fn restore(
+        &self,
+        name: &str,
+        mut fridges: MutexGuard<'_, HashMap<String, Entry>>,
+    ) -> Result<Arc<WsState>, FridgeError> {
+        let busy = Arc::new(Mutex::new(()));
+        let restoring = Arc::clone(&busy).lock_owned().await;
+
+        fridges.insert(name.to_owned(), Entry::Busy(busy));
+
+        // taken along with the registry, so that a reload in the meantime is noticed
+        let settings = self.settings();
+
+        drop(fridges);
+
+        let restored = build_fridge(
+            name,
+            settings.tiles(name),
+            settings.fridge_dimensions,
+            self.snapshot_directory.as_deref(),
+            self.history_size,
+            self.broadcast_capacity,
+            settings.move_limits,
+        )
+        .await;
+
+        let mut fridges = self.named.lock().await;
+
+        let ws_state = match restored {
+            Ok(ws_state) => ws_state,
+            Err(error) => {
+                fridges.remove(name);
+
+                return Err(FridgeError::Snapshot(error));
+            },
+        };
+
+        // the reload passed this fridge by
+        let reloaded = self.settings();
+
+        if !Arc::ptr_eq(&settings, &reloaded) {
+            apply_settings(&ws_state, &reloaded).await;
+        }
+
+        event!(Level::INFO, fridge = name, "Fridge created");
+
+        fridges.insert(name.to_owned(), Entry::Ready(Arc::clone(&ws_state)));
+
+        // only once the fridge is ready, for those waiting on it
+        drop(fridges);
+        drop(restoring);
+
+        Ok(ws_state)
+    }
+
+    /// All fridges, the default one included.
+    ///
+    /// Fridges being restored have nothing to them yet, and those being evicted are on their way out,
+    /// so both are left out.
+    pub async fn all(&self) -> Vec<Arc<WsState>> {
+        let fridges = self.named.lock().await;
+
+        let mut all = Vec::with_capacity(fridges.len() + 1);
+        all.push(self.default_fridge());
+        all.extend(fridges.values().filter_map(Entry::ready).map(Arc::clone));
+
+        all
+    }
+
+    /// Sends `message` to every poet, on every fridge.
+    pub async fn broadcast(&self, message: &ServerMessage) {
+        // every fridge shares the encoded message
+        let frame = Arc::new(Frame::new(message.clone()));
+
+        for ws_state in self.all().await {
+            ws_state.broadcast_frame(None, Arc::clone(&frame));
+        }
+    }
+
+    /// Snapshots every fridge, when snapshots are enabled.
+    pub async fn save_all(&self) {
+        let Some(snapshot_directory) = self.snapshot_directory.as_deref() else {
+            return;
+        };
+
+        for ws_state in self.all().await {
+            let path = snapshot::snapshot_path(snapshot_directory, ws_state.name());
+
+            snapshot::save_fridge(&ws_state, &path).await;
+        }
+    }
+
+    /// Snapshots and drops the fridges that have had nobody around for longer than the idle timeout.
+    ///
+    /// The snapshots are written without holding the registry. The fridges are busy meanwhile,
+    /// and poets walking up to them wait to restore them from the snapshot.
+    pub async fn evict_idle(&self) {
+        let mut evicted = Vec::new();
+
+        {
+            let mut fridges = self.named.lock().await;
+
+            let idle = fridges
+                .iter()
+                .filter(|&(_, entry)| {
+                    // the registry holds the only reference when no poets are connected
+                    entry.ready().is_some_and(|ws_state| {
+                        Arc::strong_count(ws_state) == 1 && ws_state.idle_for() > self.idle_timeout
+                    })
+                })
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<_>>();
+
+            for name in idle {
+                let busy = Arc::new(Mutex::new(()));
+                let saving = Arc::clone(&busy).lock_owned().await;
+
+                if let Some(Entry::Ready(ws_state)) =
+                    fridges.insert(name.clone(), Entry::Busy(busy))
+                {
+                    evicted.push((name, ws_state, saving));
+                }
+            }
+        }
+
+        for (name, ws_state, saving) in evicted {
+            if let Some(snapshot_directory) = self.snapshot_directory.as_deref() {
+                let path = snapshot::snapshot_path(snapshot_directory, &name);
+
+                snapshot::save_fridge(&ws_state, &path).await;
+            }
+
+            self.named.lock().await.remove(&name);
+
+            // only once it's gone, so that those waiting on it restore it from the snapshot
+            drop(saving);
+
+            event!(Level::INFO, fridge = name, "Idle fridge evicted");
+        }
+    }
+
+    pub fn idle_timeout(&self) -> Duration {
+        self.idle_timeout
+    }
+}
+
+/// Applies the settings of a reloaded configuration to a fridge, see [`Fridges::reload`].
+async fn apply_settings(ws_state: &WsState, settings: &Settings) {
+    ws_state.set_move_limits(settings.move_limits);
+
+    if ws_state.fridge_dimensions() != settings.fridge_dimensions {
+        let moved = ws_state
+            .set_fridge_dimensions(settings.fridge_dimensions)
+            .await;
+
+        event!(
+            Level::INFO,
+            fridge = ws_state.name(),
+            moved,
+            "Fridge resized"
+        );
+    }
+
+    match ws_state.add_words(settings.tiles(ws_state.name())).await {
+        Some(0) => {},
+        Some(added) => {
+            event!(Level::INFO, fridge = ws_state.name(), added, "Words added");
+        },
+        None => {
+            event!(
+                Level::WARN,
+                fridge = ws_state.name(),
+                "Words were taken away or changed, which only applies after a restart"
+            );
+        },
+    }
+}
+
+async fn build_fridge(
+    name: &str,
+    words: &[String],
+    fridge_dimensions: FridgeDimensions,
+    snapshot_directory: Option<&Path>,
+    history_size: usize,
+    broadcast_capacity: usize,
+    move_limits: MoveLimits,
+) -> Result<Arc<WsState>, eyre::Report> {
+    let snapshot = match snapshot_directory {
+        Some(snapshot_directory) => {
+            snapshot::load(&snapshot::snapshot_path(snapshot_directory, name)).await?
+        },
+        None => None,
+    };
+
+    Ok(build_ws_state(
+        name,
+        words,
+        fridge_dimensions,
+        snapshot,
+        history_size,
+        broadcast_capacity,
+        move_limits,
+    ))
+}
+
+/// Fridge names end up in URLs and in snapshot file names, so we keep them boring.
+fn is_valid_fridge_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_FRIDGE_NAME_LENGTH
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'_')
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::{Arc, RwLock};
+    use std::time::Duration;
+
+    use hashbrown::HashMap;
+    use pretty_assertions::assert_eq;
+    use tokio::sync::Mutex;
+    use uuid::Uuid;
+
+    use crate::states::config::{FridgeDimensions, MoveLimits, RateLimit};
+    use crate::words::fridges::{Entry, Fridges, Settings, build_fridge, is_valid_fridge_name};
+    use crate::words::{MoveEventParams, MoveOutcome};
+
+    const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+    /// Fridges of a couple of words, snapshotted to `snapshot_directory`.
+    async fn fridges(snapshot_directory: PathBuf) -> Fridges {
+        let settings = Settings {
+            tiles: vec!["fridge".to_owned(), "magnet".to_owned()],
+            fridge_tiles: HashMap::new(),
+            fridge_dimensions: FridgeDimensions {
+                fridge_width: 100,
+                fridge_height: 100,
+            },
+            move_limits: MoveLimits {
+                poet: RateLimit {
+                    per_second: 1.0,
+                    burst: 5,
+                },
+                address: None,
+                max_dropped: 10,
+            },
+        };
+
+        let default = build_fridge(
+            "default",
+            &settings.tiles,
+            settings.fridge_dimensions,
+            None,
+            100,
+            64,
+            settings.move_limits,
+        )
+        .await
+        .unwrap();
+
+        Fridges {
+            default,
+            named: Mutex::new(HashMap::new()),
+            settings: RwLock::new(Arc::new(settings)),
+            snapshot_directory: Some(snapshot_directory),
+            idle_timeout: IDLE_TIMEOUT,
+            capacity: 10,
+            history_size: 100,
+            broadcast_capacity: 64,
+        }
+    }
+
+    #[tokio::test]
+    async fn evicted_fridges_are_restored_from_their_snapshot() {
+        let snapshot_directory = std::env::temp_dir().join(format!("magwords-{}", Uuid::now_v7()));
+        tokio::fs::create_dir_all(&snapshot_directory)
+            .await
+            .unwrap();
+
+        let fridges = fridges(snapshot_directory.clone()).await;
+
+        {
+            let ws_state = fridges.get_or_create("kitchen").await.unwrap();
+            let move_event = MoveEventParams {
+                id: 1,
+                v: 0,
+                x: 42,
+                y: 24,
+            };
+
+            assert!(
+                matches!(
+                    ws_state.apply_move(move_event, None).await,
+                    MoveOutcome::Moved(_)
+                ),
+                "moved"
+            );
+        }
+
+        tokio::time::pause();
+        tokio::time::advance(IDLE_TIMEOUT * 2).await;
+        tokio::time::resume();
+
+        fridges.evict_idle().await;
+
+        assert!(fridges.get("kitchen").await.is_none(), "evicted");
+        assert!(
+            fridges.named.lock().await.is_empty(),
+            "nothing left of it in the registry"
+        );
+
+        let ws_state = fridges.get_or_create("kitchen").await.unwrap();
+        let word = ws_state.word(1).await.unwrap();
+
+        assert_eq!((word.v, word.x, word.y), (1, 42, 24), "where it was moved");
+
+        tokio::fs::remove_dir_all(&snapshot_directory)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn fridges_left_busy_are_restored() {
+        let fridges =
+            fridges(std::env::temp_dir().join(format!("magwords-{}", Uuid::now_v7()))).await;
+
+        // as left by a restore that was called off
+        fridges
+            .named
+            .lock()
+            .await
+            .insert("kitchen".to_owned(), Entry::Busy(Arc::new(Mutex::new(()))));
+
+        assert!(fridges.get("kitchen").await.is_none(), "not there yet");
+        assert!(
+            fridges.get_or_create("kitchen").await.is_ok(),
+            "restored all the same"
+        );
+        assert!(fridges.get("kitchen").await.is_some(), "there now");
+    }
+
+    #[tokio::test]
+    async fn busy_fridges_dont_hold_up_the_others() {
+        let fridges =
+            fridges(std::env::temp_dir().join(format!("magwords-{}", Uuid::now_v7()))).await;
+
+        let busy = Arc::new(Mutex::new(()));
+        let restoring = Arc::clone(&busy).lock_owned().await;
+
+        fridges
+            .named
+            .lock()
+            .await
+            .insert("kitchen".to_owned(), Entry::Busy(busy));
+
+        assert!(
+            fridges.get_or_create("hallway").await.is_ok(),
+            "another fridge is created meanwhile"
+        );
+        assert_eq!(
+            fridges.all().await.len(),
+            2,
+            "the default fridge and the hallway"
+        );
+
+        let waiting = fridges.get_or_create("kitchen");
+        tokio::pin!(waiting);
+
+        assert!(
+            tokio::time::timeout(Duration::from_millis(10), &mut waiting)
+                .await
+                .is_err(),
+            "waits on the busy fridge"
+        );
+
+        drop(restoring);
+
+        assert!(waiting.await.is_ok(), "restored once it's no longer busy");
+    }
+
+    #[test]
+    fn valid_fridge_names() {
+        assert!(is_valid_fridge_name("default"), "plain name");
+        assert!(
+            is_valid_fridge_name("squad-7_blue"),
+            "dashes, digits and underscores"
+        );
+    }
+
+    #[test]
+    fn invalid_fridge_names() {
+        assert!(!is_valid_fridge_name(""), "empty");
+        assert!(!is_valid_fridge_name("Squad"), "uppercase");
+        assert!(!is_valid_fridge_name("../etc"), "path traversal");
+        assert!(!is_valid_fridge_name(&"a".repeat(65)), "too long");
+    }
+}
@@ -0,0 +1,262 @@
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use hashbrown::HashMap;
+use tokio::time::Instant;
+
+/// How long a poet may hold on to a word without dragging, moving or releasing it.
+pub const GRAB_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Grab {
+    client_id: u64,
+    /// When the poet grabbed the word, or last dragged it.
+    since: Instant,
+}
+
+impl Grab {
+    fn is_expired(&self) -> bool {
+        self.since.elapsed() > GRAB_TIMEOUT
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum GrabOutcome {
+    /// Grabbed, and let go of the word held before, if any.
+    Grabbed {
+        released: Option<usize>,
+    },
+    HeldByOther,
+    UnknownWord,
+}
+
+/// The words poets are currently dragging, which nobody else may move.
+/// Every poet holds on to at most one word.
+#[derive(Default)]
+pub struct Grabs {
+    held: Mutex<HashMap<usize, Grab>>,
+}
+
+impl Grabs {
+    /// Grabs word `id` for `client_id`, letting go of whatever it held before.
+    ///
+    /// Grabbing a word held already doesn't hold it any longer, or a poet could keep it forever
+    /// without dragging it, see [`Grabs::touch`].
+    pub fn grab(&self, id: usize, client_id: u64) -> GrabOutcome {
+        let mut held = self.held.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(grab) = held.get(&id).filter(|grab| !grab.is_expired()) {
+            if grab.client_id != client_id {
+                return GrabOutcome::HeldByOther;
+            }
+
+            // a poet holds on to one word only, so there's nothing else to let go of
+            return GrabOutcome::Grabbed { released: None };
+        }
+
+        let previous = held
+            .iter()
+            .find(|&(&held_id, grab)| grab.client_id == client_id && held_id != id)
+            .map(|(&held_id, _)| held_id);
+
+        if let Some(previous) = previous {
+            held.remove(&previous);
+        }
+
+        held.insert(
+            id,
+            Grab {
+                client_id,
+                since: Instant::now(),
+            },
+        );
+
+        GrabOutcome::Grabbed { released: previous }
+    }
+
+    /// Keeps word `id` held for as long as `client_id` drags it, if they hold it.
+    pub fn touch(&self, id: usize, client_id: u64) {
+        if let Some(grab) = self
+            .held
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get_mut(&id)
+            .filter(|grab| grab.client_id == client_id && !grab.is_expired())
+        {
+            grab.since = Instant::now();
+        }
+    }
+
+    /// Lets go of word `id`, if `client_id` holds it. Returns whether it did.
+    pub fn release(&self, id: usize, client_id: u64) -> bool {
+        let mut held = self.held.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if held
+            .get(&id)
+            .is_some_and(|grab| grab.client_id == client_id)
+        {
+            held.remove(&id);
+
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Whether word `id` is held by someone other than `client_id`.
+    pub fn is_held_by_other(&self, id: usize, client_id: Option<u64>) -> bool {
+        self.held
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&id)
+            .is_some_and(|grab| Some(grab.client_id) != client_id && !grab.is_expired())
+    }
+
+    /// The words currently held by anyone.
+    pub fn held_words(&self) -> Vec<usize> {
+        self.held
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .filter(|&(_, grab)| !grab.is_expired())
+            .map(|(&id, _)| id)
+            .collect()
+    }
+
+    /// Lets go of everything `client_id` holds, returning the words.
+    pub fn release_all(&self, client_id: u64) -> Vec<usize> {
+        self.remove_where(|grab| grab.client_id == client_id)
+    }
+
+    /// Lets go of everything held for too long, returning the words.
+    pub fn expire(&self) -> Vec<usize> {
+        self.remove_where(Grab::is_expired)
+    }
+
+    fn remove_where<P>(&self, predicate: P) -> Vec<usize>
+    where
+        P: Fn(&Grab) -> bool,
+    {
+        let mut held = self.held.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let mut removed = Vec::new();
+
+        held.retain(|&id, grab| {
+            if predicate(grab) {
+                removed.push(id);
+
+                false
+            } else {
+                true
+            }
+        });
+
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::words::grabs::{GRAB_TIMEOUT, GrabOutcome, Grabs};
+
+    #[test]
+    fn grabbed_words_are_held_by_their_grabber_only() {
+        let grabs = Grabs::default();
+
+        assert_eq!(grabs.grab(1, 10), GrabOutcome::Grabbed { released: None });
+
+        assert!(!grabs.is_held_by_other(1, Some(10)), "held by us");
+        assert!(grabs.is_held_by_other(1, Some(11)), "held by 10");
+        assert!(grabs.is_held_by_other(1, None), "held by 10");
+        assert!(!grabs.is_held_by_other(2, Some(11)), "nobody holds it");
+
+        assert_eq!(grabs.grab(1, 11), GrabOutcome::HeldByOther);
+
+        assert!(!grabs.release(1, 11), "11 doesn't hold it");
+        assert!(grabs.release(1, 10), "10 holds it");
+
+        assert_eq!(grabs.grab(1, 11), GrabOutcome::Grabbed { released: None });
+    }
+
+    #[test]
+    fn one_word_per_poet() {
+        let grabs = Grabs::default();
+
+        assert_eq!(grabs.grab(1, 10), GrabOutcome::Grabbed { released: None });
+        assert_eq!(
+            grabs.grab(2, 10),
+            GrabOutcome::Grabbed { released: Some(1) }
+        );
+
+        assert!(!grabs.is_held_by_other(1, Some(11)), "let go of");
+        assert!(grabs.is_held_by_other(2, Some(11)), "held by 10");
+    }
+
+    #[test]
+    fn release_all() {
+        let grabs = Grabs::default();
+
+        assert_eq!(grabs.grab(1, 10), GrabOutcome::Grabbed { released: None });
+        assert_eq!(grabs.grab(2, 11), GrabOutcome::Grabbed { released: None });
+
+        assert_eq!(grabs.release_all(10), [1]);
+
+        assert!(!grabs.is_held_by_other(1, Some(11)), "released");
+        assert!(grabs.is_held_by_other(2, Some(10)), "still held by 11");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn grabs_expire() {
+        let grabs = Grabs::default();
+
+        assert_eq!(grabs.grab(1, 10), GrabOutcome::Grabbed { released: None });
+
+        tokio::time::advance(GRAB_TIMEOUT * 2).await;
+
+        assert!(!grabs.is_held_by_other(1, Some(11)), "expired");
+        assert_eq!(grabs.expire(), [1]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn grabbing_again_doesnt_hold_any_longer() {
+        let grabs = Grabs::default();
+
+        assert_eq!(grabs.grab(1, 10), GrabOutcome::Grabbed { released: None });
+
+        tokio::time::advance(GRAB_TIMEOUT / 2).await;
+
+        assert_eq!(grabs.grab(1, 10), GrabOutcome::Grabbed { released: None });
+
+        tokio::time::advance(GRAB_TIMEOUT / 2 + Duration::from_millis(1)).await;
+
+        assert!(!grabs.is_held_by_other(1, Some(11)), "expired all the same");
+        assert_eq!(grabs.expire(), [1]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn dragging_holds_on() {
+        let grabs = Grabs::default();
+
+        assert_eq!(grabs.grab(1, 10), GrabOutcome::Grabbed { released: None });
+
+        for _ in 0..3 {
+            tokio::time::advance(GRAB_TIMEOUT / 2).await;
+
+            grabs.touch(1, 10);
+            grabs.touch(1, 11);
+        }
+
+        assert!(grabs.is_held_by_other(1, Some(11)), "still dragged by 10");
+        assert!(grabs.expire().is_empty(), "nothing expired");
+
+        tokio::time::advance(GRAB_TIMEOUT + Duration::from_millis(1)).await;
+
+        assert!(
+            !grabs.is_held_by_other(1, Some(11)),
+            "expired once the dragging stopped, 11 dragging it changes nothing"
+        );
+    }
+}
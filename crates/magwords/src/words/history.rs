@@ -0,0 +1,202 @@
+use std::collections::VecDeque;
+use std::sync::{Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hashbrown::HashMap;
+use serde::Serialize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Position {
+    pub x: u32,
+    pub y: u32,
+}
+
+/// A move that made it onto the fridge.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct HistoryEntry {
+    pub id: usize,
+    pub from: Position,
+    pub to: Position,
+    /// The poet who made the move, `None` when it came in over the REST API or was made by an admin.
+    pub client_id: Option<u64>,
+    /// Milliseconds since the Unix epoch.
+    pub at: u64,
+}
+
+/// Milliseconds since the Unix epoch, the unit of [`HistoryEntry::at`].
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| {
+            u64::try_from(since_epoch.as_millis()).unwrap_or(u64::MAX)
+        })
+}
+
+/// The latest moves on a fridge, oldest first. Once full, the oldest moves are forgotten.
+pub struct History {
+    entries: Mutex<Entries>,
+    capacity: usize,
+}
+
+struct Entries {
+    entries: VecDeque<HistoryEntry>,
+    /// When the newest move we've forgotten was made.
+    forgotten_until: Option<u64>,
+}
+
+/// How far a fridge could be reverted.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Revert {
+    /// Where to put words to get them back to where they were, for the words that moved since.
+    pub positions: HashMap<usize, Position>,
+    /// Whether we remember every move since, or had to stop at the oldest one we know.
+    pub complete: bool,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(Entries {
+                entries: VecDeque::with_capacity(capacity.min(1024)),
+                forgotten_until: None,
+            }),
+            capacity,
+        }
+    }
+
+    pub fn record(&self, entry: HistoryEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if entries.entries.len() >= self.capacity {
+            if let Some(forgotten) = entries.entries.pop_front() {
+                entries.forgotten_until = Some(forgotten.at);
+            }
+        }
+
+        entries.entries.push_back(entry);
+    }
+
+    /// The moves made from `from` up to and including `to`, oldest first.
+    pub fn between(&self, from: u64, to: u64) -> Vec<HistoryEntry> {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entries
+            .iter()
+            .filter(|&entry| (from..=to).contains(&entry.at))
+            .cloned()
+            .collect()
+    }
+
+    /// Where the words that moved after `at` were at that time.
+    pub fn revert_to(&self, at: u64) -> Revert {
+        let entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let mut positions = HashMap::new();
+
+        // newest to oldest, so that each word ends up where it was before its oldest move after `at`
+        for entry in entries
+            .entries
+            .iter()
+            .rev()
+            .take_while(|entry| entry.at > at)
+        {
+            positions.insert(entry.id, entry.from);
+        }
+
+        Revert {
+            positions,
+            complete: entries
+                .forgotten_until
+                .is_none_or(|forgotten_until| forgotten_until <= at),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hashbrown::HashMap;
+    use pretty_assertions::assert_eq;
+
+    use crate::words::history::{History, HistoryEntry, Position, Revert};
+
+    fn entry(id: usize, from: u32, to: u32, at: u64) -> HistoryEntry {
+        HistoryEntry {
+            id,
+            from: Position { x: from, y: from },
+            to: Position { x: to, y: to },
+            client_id: Some(1),
+            at,
+        }
+    }
+
+    #[test]
+    fn reverts_to_before_the_oldest_move_since() {
+        let history = History::new(10);
+
+        history.record(entry(1, 0, 1, 100));
+        history.record(entry(1, 1, 2, 200));
+        history.record(entry(2, 5, 6, 300));
+        history.record(entry(1, 2, 3, 400));
+
+        assert_eq!(
+            history.revert_to(150),
+            Revert {
+                positions: HashMap::from_iter([
+                    (1, Position { x: 1, y: 1 }),
+                    (2, Position { x: 5, y: 5 })
+                ]),
+                complete: true,
+            }
+        );
+
+        assert_eq!(
+            history.revert_to(400),
+            Revert {
+                positions: HashMap::new(),
+                complete: true,
+            }
+        );
+    }
+
+    #[test]
+    fn moves_between() {
+        let history = History::new(10);
+
+        history.record(entry(1, 0, 1, 100));
+        history.record(entry(1, 1, 2, 200));
+        history.record(entry(2, 5, 6, 300));
+
+        assert_eq!(
+            history.between(200, 300),
+            [entry(1, 1, 2, 200), entry(2, 5, 6, 300)]
+        );
+        assert_eq!(history.between(101, 199), []);
+    }
+
+    #[test]
+    fn forgets_the_oldest_moves() {
+        let history = History::new(2);
+
+        history.record(entry(1, 0, 1, 100));
+        history.record(entry(1, 1, 2, 200));
+        history.record(entry(1, 2, 3, 300));
+
+        assert_eq!(
+            history.revert_to(50),
+            Revert {
+                positions: HashMap::from_iter([(1, Position { x: 1, y: 1 })]),
+                complete: false,
+            }
+        );
+
+        assert!(
+            history.revert_to(100).complete,
+            "we remember everything after 100"
+        );
+    }
+}
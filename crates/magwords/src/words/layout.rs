@@ -0,0 +1,83 @@
+use crate::states::config::FridgeDimensions;
+use crate::words::WordInfo;
+use crate::words::history::Position;
+
+/// The spot on a fridge of `fridge_dimensions` nearest to `position`, which is `position` itself when it's on the fridge.
+pub fn clamp(position: Position, fridge_dimensions: FridgeDimensions) -> Position {
+    Position {
+        x: position.x.min(fridge_dimensions.fridge_width),
+        y: position.y.min(fridge_dimensions.fridge_height),
+    }
+}
+
+/// Moves the words that are off a fridge of `fridge_dimensions` onto its edge, as they are when the fridge
+/// shrank since they were put there. `place` puts a word on the spot it's handed. Returns how many words moved.
+pub fn clamp_all<P>(
+    words: &mut [WordInfo],
+    fridge_dimensions: FridgeDimensions,
+    mut place: P,
+) -> usize
+where
+    P: FnMut(&mut WordInfo, Position),
+{
+    let mut moved = 0;
+
+    for word in words {
+        let position = Position {
+            x: word.x,
+            y: word.y,
+        };
+
+        let clamped = clamp(position, fridge_dimensions);
+
+        if clamped != position {
+            place(word, clamped);
+            moved += 1;
+        }
+    }
+
+    moved
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::states::config::FridgeDimensions;
+    use crate::words::WordInfo;
+    use crate::words::layout::clamp_all;
+
+    fn word(id: usize, x: u32, y: u32) -> WordInfo {
+        WordInfo {
+            id,
+            word: "magnet".into(),
+            v: 0,
+            x,
+            y,
+        }
+    }
+
+    #[test]
+    fn clamps_words_off_the_fridge() {
+        let mut words = vec![word(0, 10, 20), word(1, 150, 20), word(2, 150, 300)];
+
+        let moved = clamp_all(
+            &mut words,
+            FridgeDimensions {
+                fridge_width: 100,
+                fridge_height: 200,
+            },
+            |word, to| {
+                word.x = to.x;
+                word.y = to.y;
+            },
+        );
+
+        assert_eq!(moved, 2, "two were off the fridge");
+        assert_eq!(
+            words,
+            vec![word(0, 10, 20), word(1, 100, 20), word(2, 100, 200)],
+            "pulled onto the edge, the one on the fridge stays"
+        );
+    }
+}
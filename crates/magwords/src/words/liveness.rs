@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Whether a poet answers our heartbeats, each within the pong timeout.
+#[derive(Debug)]
+pub struct Liveness {
+    pong_timeout: Duration,
+    /// When we sent the oldest heartbeat the poet didn't answer yet.
+    unanswered_since: Option<Instant>,
+}
+
+impl Liveness {
+    pub fn new(pong_timeout: Duration) -> Self {
+        Self {
+            pong_timeout,
+            unanswered_since: None,
+        }
+    }
+
+    /// Counts a heartbeat sent to the poet. Returns whether they're still around, which they aren't
+    /// when they left an earlier heartbeat unanswered for longer than the pong timeout.
+    pub fn heartbeat(&mut self) -> bool {
+        let now = Instant::now();
+
+        if let Some(sent) = self.unanswered_since {
+            return now.saturating_duration_since(sent) <= self.pong_timeout;
+        }
+
+        self.unanswered_since = Some(now);
+
+        true
+    }
+
+    pub fn pong(&mut self) {
+        self.unanswered_since = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::words::liveness::Liveness;
+
+    #[tokio::test(start_paused = true)]
+    async fn measures_from_the_unanswered_heartbeat() {
+        let mut liveness = Liveness::new(Duration::from_secs(5));
+
+        assert!(liveness.heartbeat(), "first heartbeat");
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+        liveness.pong();
+
+        // heartbeats further apart than the pong timeout
+        tokio::time::advance(Duration::from_secs(10)).await;
+
+        assert!(liveness.heartbeat(), "answered the previous one");
+
+        tokio::time::advance(Duration::from_secs(3)).await;
+
+        assert!(liveness.heartbeat(), "still within the pong timeout");
+
+        tokio::time::advance(Duration::from_secs(3)).await;
+
+        assert!(!liveness.heartbeat(), "left one unanswered for too long");
+    }
+}
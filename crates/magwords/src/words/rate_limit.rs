@@ -0,0 +1,110 @@
+use tokio::time::Instant;
+
+use crate::states::config::RateLimit;
+
+/// Allows `limit.burst` moves at once, and refills at `limit.per_second`.
+#[derive(Debug)]
+pub struct TokenBucket {
+    limit: RateLimit,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            tokens: f64::from(limit.burst),
+            updated: Instant::now(),
+        }
+    }
+
+    /// Whether there are `count` tokens to take, without taking them.
+    pub fn can_take(&mut self, count: u32) -> bool {
+        self.refill(Instant::now());
+
+        self.tokens >= f64::from(count)
+    }
+
+    /// Takes `count` tokens, if there are that many.
+    pub fn try_take(&mut self, count: u32) -> bool {
+        if !self.can_take(count) {
+            return false;
+        }
+
+        self.tokens -= f64::from(count);
+
+        true
+    }
+
+    /// Switches to `limit`, keeping the tokens left, up to its burst.
+    pub fn set_limit(&mut self, limit: RateLimit) {
+        self.refill(Instant::now());
+
+        self.limit = limit;
+        self.tokens = self.tokens.min(f64::from(limit.burst));
+    }
+
+    /// Whether the bucket filled back up, after which forgetting it changes nothing.
+    pub fn is_full(&mut self) -> bool {
+        self.refill(Instant::now());
+
+        self.tokens >= f64::from(self.limit.burst)
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+
+        self.tokens = elapsed
+            .mul_add(self.limit.per_second, self.tokens)
+            .min(f64::from(self.limit.burst));
+        self.updated = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::states::config::RateLimit;
+    use crate::words::rate_limit::TokenBucket;
+
+    #[tokio::test(start_paused = true)]
+    async fn allows_bursts_and_refills() {
+        let mut bucket = TokenBucket::new(RateLimit {
+            per_second: 2.0,
+            burst: 3,
+        });
+
+        assert!(bucket.is_full(), "starts full");
+
+        for _ in 0..3 {
+            assert!(bucket.try_take(1), "within the burst");
+        }
+
+        assert!(!bucket.try_take(1), "burst used up");
+
+        tokio::time::advance(Duration::from_millis(500)).await;
+
+        assert!(bucket.can_take(1), "refilled one");
+        assert!(bucket.can_take(1), "without taking it");
+        assert!(bucket.try_take(1), "refilled one");
+        assert!(!bucket.try_take(1), "and only one");
+
+        tokio::time::advance(Duration::from_secs(10)).await;
+
+        assert!(bucket.is_full(), "refills up to the burst");
+        assert!(!bucket.try_take(4), "more than the burst");
+        assert!(bucket.try_take(3), "the whole burst");
+
+        bucket.set_limit(RateLimit {
+            per_second: 1.0,
+            burst: 1,
+        });
+
+        tokio::time::advance(Duration::from_secs(10)).await;
+
+        assert!(bucket.try_take(1), "refills up to the new burst");
+        assert!(!bucket.try_take(1), "and no further");
+    }
+}
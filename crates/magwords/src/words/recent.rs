@@ -0,0 +1,113 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use crate::words::ServerMessage;
+use crate::words::frame::Frame;
+
+/// How many broadcasts a poet can miss and still resume, rather than get a fresh copy of the fridge.
+pub const RESUMABLE_BROADCASTS: usize = 1024;
+
+/// The latest numbered broadcasts of a fridge, for poets who reconnect.
+pub struct Recent {
+    /// The sequence number of the latest broadcast.
+    seq: u64,
+    frames: VecDeque<(u64, Arc<Frame>)>,
+}
+
+impl Default for Recent {
+    fn default() -> Self {
+        Self {
+            seq: 0,
+            frames: VecDeque::with_capacity(RESUMABLE_BROADCASTS),
+        }
+    }
+}
+
+impl Recent {
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Numbers `message`, and remembers it.
+    pub fn record(&mut self, message: ServerMessage) -> Arc<Frame> {
+        self.seq += 1;
+
+        let frame = Arc::new(Frame::sequenced(message, self.seq));
+
+        if self.frames.len() >= RESUMABLE_BROADCASTS {
+            self.frames.pop_front();
+        }
+
+        self.frames.push_back((self.seq, Arc::clone(&frame)));
+
+        frame
+    }
+
+    /// The broadcasts after `seq`, or `None` when we don't remember all of them.
+    pub fn since(&self, seq: u64) -> Option<Vec<Arc<Frame>>> {
+        let oldest = self
+            .frames
+            .front()
+            .map_or(self.seq + 1, |&(oldest, _)| oldest);
+
+        // a sequence number from the future was made up, or is from another run
+        if seq > self.seq || seq + 1 < oldest {
+            return None;
+        }
+
+        Some(
+            self.frames
+                .iter()
+                .filter(|&&(frame_seq, _)| frame_seq > seq)
+                .map(|&(_, ref frame)| Arc::clone(frame))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::words::ServerMessage;
+    use crate::words::recent::{RESUMABLE_BROADCASTS, Recent};
+
+    fn seqs(recent: &Recent, seq: u64) -> Option<Vec<u64>> {
+        recent
+            .since(seq)
+            .map(|frames| frames.iter().filter_map(|frame| frame.seq()).collect())
+    }
+
+    #[test]
+    fn resumes_from_what_it_remembers() {
+        let mut recent = Recent::default();
+
+        assert_eq!(seqs(&recent, 0), Some(vec![]), "nothing happened yet");
+
+        for id in 0..3 {
+            recent.record(ServerMessage::Grabbed { id });
+        }
+
+        assert_eq!(recent.seq(), 3, "numbered from 1");
+        assert_eq!(seqs(&recent, 0), Some(vec![1, 2, 3]), "everything");
+        assert_eq!(seqs(&recent, 2), Some(vec![3]), "missed one");
+        assert_eq!(seqs(&recent, 3), Some(vec![]), "up to date");
+        assert_eq!(seqs(&recent, 4), None, "from the future");
+    }
+
+    #[test]
+    fn forgets_the_oldest() {
+        let mut recent = Recent::default();
+
+        for id in 0..RESUMABLE_BROADCASTS + 2 {
+            recent.record(ServerMessage::Grabbed { id });
+        }
+
+        assert_eq!(seqs(&recent, 1), None, "missed too much");
+        assert_eq!(
+            seqs(&recent, 2).map(|seqs| seqs.len()),
+            Some(RESUMABLE_BROADCASTS),
+            "missed just enough"
+        );
+    }
+}
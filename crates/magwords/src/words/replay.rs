@@ -0,0 +1,215 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::ws::{Message, WebSocket};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use tracing::{Level, event};
+
+use crate::words::codec::Codec;
+use crate::words::{MoveEventParams, ServerMessage, WireWords, WsState, send_message};
+
+/// Longest pause between two moves of a replay, so that nobody sits through a quiet night.
+const MAX_REPLAY_GAP: Duration = Duration::from_secs(2);
+
+const MAX_REPLAY_SPEED: f64 = 1000.0;
+
+#[derive(Debug)]
+pub enum ReplayError {
+    InvalidRange,
+    InvalidSpeed,
+}
+
+impl IntoResponse for ReplayError {
+    fn into_response(self) -> Response {
+        match self {
+            ReplayError::InvalidRange => {
+                (StatusCode::BAD_REQUEST, "`from` comes after `to`").into_response()
+            },
+            ReplayError::InvalidSpeed => (
+                StatusCode::BAD_REQUEST,
+                "`speed` must be more than 0, and at most 1000",
+            )
+                .into_response(),
+        }
+    }
+}
+
+/// Which part of a fridge's history to replay, and how fast.
+#[derive(Deserialize)]
+pub struct ReplayParams {
+    /// Milliseconds since the Unix epoch, defaults to the oldest move we remember.
+    from: Option<u64>,
+    /// Milliseconds since the Unix epoch, defaults to now.
+    to: Option<u64>,
+    /// How many times faster than it happened, for replays over `/ws`. Defaults to 1.
+    speed: Option<f64>,
+}
+
+/// A validated [`ReplayParams`].
+#[derive(Clone, Copy, Debug)]
+pub struct Replay {
+    pub from: u64,
+    pub to: u64,
+    pub speed: f64,
+}
+
+impl ReplayParams {
+    /// # Errors
+    /// * `from` comes after `to`
+    /// * `speed` isn't a number between 0 (exclusive) and [`MAX_REPLAY_SPEED`]
+    pub fn replay(&self) -> Result<Replay, ReplayError> {
+        let from = self.from.unwrap_or(0);
+        let to = self.to.unwrap_or(u64::MAX);
+
+        if from > to {
+            return Err(ReplayError::InvalidRange);
+        }
+
+        let speed = self.speed.unwrap_or(1.0);
+
+        if !(speed > 0.0 && speed <= MAX_REPLAY_SPEED) {
+            return Err(ReplayError::InvalidSpeed);
+        }
+
+        Ok(Replay { from, to, speed })
+    }
+}
+
+/// Shows a viewer the fridge as it was at `replay.from`, and then the moves made since, up to `replay.to`,
+/// spaced out like they happened, `replay.speed` times faster. Anything the viewer sends is ignored.
+pub async fn handle_replay(
+    mut socket: WebSocket,
+    state: Arc<WsState>,
+    address: SocketAddr,
+    codec: Codec,
+    replay: Replay,
+) {
+    // replay viewers aren't poets and don't get a client id, this one only shows up in logs
+    let client_id = u64::MAX;
+
+    event!(Level::DEBUG, %address, fridge = state.name, ?replay, "Replay viewer connected");
+
+    let config = ServerMessage::from(state.fridge_dimensions());
+
+    if send_message(&mut socket, &config, codec, client_id, address)
+        .await
+        .is_break()
+    {
+        return;
+    }
+
+    let (words, complete) = state.words_before(replay.from).await;
+
+    if !complete {
+        event!(Level::DEBUG, %address, fridge = state.name, "History doesn't go back far enough, replay starts from the oldest move we remember");
+    }
+
+    if send_message(
+        &mut socket,
+        &WireWords {
+            r#type: "words",
+            data: &words,
+        },
+        codec,
+        client_id,
+        address,
+    )
+    .await
+    .is_break()
+    {
+        return;
+    }
+
+    let entries = state.history_between(replay.from, replay.to);
+
+    let mut previous_at = entries.first().map_or(0, |entry| entry.at);
+
+    for entry in entries {
+        // the clock may have gone backwards in between
+        let gap = Duration::from_millis(entry.at.saturating_sub(previous_at))
+            .div_f64(replay.speed)
+            .min(MAX_REPLAY_GAP);
+
+        previous_at = entry.at;
+
+        tokio::select! {
+            () = tokio::time::sleep(gap) => {},
+            () = closed(&mut socket) => return,
+        }
+
+        let message = ServerMessage::Move(MoveEventParams {
+            id: entry.id,
+            // the viewer doesn't move words, so it has no use for versions
+            v: 0,
+            x: entry.to.x,
+            y: entry.to.y,
+        });
+
+        if send_message(&mut socket, &message, codec, client_id, address)
+            .await
+            .is_break()
+        {
+            return;
+        }
+    }
+
+    // leave the poem up until the viewer is done looking at it
+    closed(&mut socket).await;
+
+    event!(Level::TRACE, %address, fridge = state.name, "Replay viewer disconnected");
+}
+
+/// Completes when the viewer goes away.
+async fn closed(socket: &mut WebSocket) {
+    loop {
+        match socket.recv().await {
+            Some(Ok(Message::Close(_)) | Err(_)) | None => return,
+            Some(Ok(_)) => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::words::replay::{ReplayError, ReplayParams};
+
+    fn params(from: Option<u64>, to: Option<u64>, speed: Option<f64>) -> ReplayParams {
+        ReplayParams { from, to, speed }
+    }
+
+    #[test]
+    fn defaults_to_everything_in_real_time() {
+        let replay = params(None, None, None).replay().unwrap();
+
+        assert_eq!(replay.from, 0, "from the start");
+        assert_eq!(replay.to, u64::MAX, "until now");
+        assert!((replay.speed - 1.0).abs() < f64::EPSILON, "real time");
+    }
+
+    #[test]
+    fn rejects_invalid_params() {
+        assert!(
+            matches!(
+                params(Some(2), Some(1), None).replay(),
+                Err(ReplayError::InvalidRange)
+            ),
+            "backwards range"
+        );
+
+        for speed in [0.0, -1.0, f64::NAN, f64::INFINITY, 1001.0] {
+            assert!(
+                matches!(
+                    params(None, None, Some(speed)).replay(),
+                    Err(ReplayError::InvalidSpeed)
+                ),
+                "speed {}",
+                speed
+            );
+        }
+    }
+}
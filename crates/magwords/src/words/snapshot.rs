@@ -0,0 +1,163 @@
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{self, Context as _};
+use serde::{Deserialize, Serialize};
+use tracing::{Level, event};
+
+use crate::states::config::FridgeDimensions;
+use crate::words::{WordInfo, WsState};
+
+/// The persisted layout of a fridge.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Snapshot {
+    pub fridge_dimensions: FridgeDimensions,
+    pub words: Vec<WordInfo>,
+}
+
+impl Snapshot {
+    /// Whether this snapshot was taken of a fridge with the same words, in the same order.
+    pub fn matches(&self, words: &[String]) -> bool {
+        self.words.len() == words.len() && self.is_prefix_of(words)
+    }
+
+    /// Whether this snapshot was taken of a fridge with the first of `words`, in the same order,
+    /// as it is when words were added to the word lists since.
+    pub fn is_prefix_of(&self, words: &[String]) -> bool {
+        self.words.len() <= words.len()
+            && self
+                .words
+                .iter()
+                .zip(words)
+                .enumerate()
+                .all(|(i, (word_info, word))| word_info.id == i && word_info.word == *word)
+    }
+}
+
+/// Where the snapshot of the fridge called `name` lives. Fridge names are validated, so they're safe to use as a file name.
+pub fn snapshot_path(directory: &Path, name: &str) -> PathBuf {
+    directory.join(format!("{}.json", name))
+}
+
+/// Reads the snapshot at `path`, if there is one.
+///
+/// # Errors
+/// * The snapshot exists but couldn't be read
+/// * The snapshot isn't valid
+pub async fn load(path: &Path) -> Result<Option<Snapshot>, eyre::Report> {
+    let raw = match tokio::fs::read(path).await {
+        Ok(raw) => raw,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => {
+            return Err(eyre::Report::new(error))
+                .wrap_err_with(|| format!("Failed to read snapshot {}", path.display()));
+        },
+    };
+
+    serde_json::from_slice(&raw)
+        .map(Some)
+        .wrap_err_with(|| format!("Failed to parse snapshot {}", path.display()))
+}
+
+/// Writes the snapshot next to `path` first, and then moves it in place,
+/// so that a crash halfway through never leaves a truncated snapshot behind.
+///
+/// # Errors
+/// * Couldn't write or move the snapshot
+pub async fn save(path: &Path, snapshot: &Snapshot) -> Result<(), eyre::Report> {
+    let json = serde_json::to_vec(snapshot)?;
+
+    let temporary_path = path.with_extension("json.tmp");
+
+    tokio::fs::write(&temporary_path, json)
+        .await
+        .wrap_err_with(|| format!("Failed to write snapshot {}", temporary_path.display()))?;
+
+    tokio::fs::rename(&temporary_path, path)
+        .await
+        .wrap_err_with(|| format!("Failed to move snapshot into {}", path.display()))
+}
+
+/// Snapshots the fridge, and logs instead of failing, as a failed snapshot shouldn't bring the fridge down.
+pub async fn save_fridge(ws_state: &WsState, path: &Path) {
+    let snapshot = ws_state.snapshot().await;
+
+    match save(path, &snapshot).await {
+        Ok(()) => {
+            event!(Level::DEBUG, path = %path.display(), "Fridge layout saved");
+        },
+        Err(error) => {
+            event!(Level::ERROR, ?error, path = %path.display(), "Failed to save fridge layout");
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::states::config::FridgeDimensions;
+    use crate::words::WordInfo;
+    use crate::words::snapshot::Snapshot;
+
+    fn snapshot_of(words: &[&str]) -> Snapshot {
+        Snapshot {
+            fridge_dimensions: FridgeDimensions {
+                fridge_width: 100,
+                fridge_height: 100,
+            },
+            words: words
+                .iter()
+                .enumerate()
+                .map(|(id, &word)| WordInfo {
+                    id,
+                    word: word.into(),
+                    v: 0,
+                    x: 1,
+                    y: 2,
+                })
+                .collect(),
+        }
+    }
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|&word| word.to_owned()).collect()
+    }
+
+    #[test]
+    fn matches_same_word_list() {
+        let snapshot = snapshot_of(&["fridge", "magnet"]);
+
+        assert!(
+            snapshot.matches(&words(&["fridge", "magnet"])),
+            "same words should match"
+        );
+    }
+
+    #[test]
+    fn rejects_different_word_list() {
+        let snapshot = snapshot_of(&["fridge", "magnet"]);
+
+        assert!(
+            !snapshot.matches(&words(&["magnet", "fridge"])),
+            "order matters"
+        );
+        assert!(!snapshot.matches(&words(&["fridge"])), "missing word");
+        assert!(
+            !snapshot.matches(&words(&["fridge", "magnet", "poem"])),
+            "extra word"
+        );
+    }
+
+    #[test]
+    fn grows_into_longer_word_list() {
+        let snapshot = snapshot_of(&["fridge", "magnet"]);
+
+        assert!(
+            snapshot.is_prefix_of(&words(&["fridge", "magnet", "poem"])),
+            "words added at the end"
+        );
+        assert!(
+            !snapshot.is_prefix_of(&words(&["poem", "fridge", "magnet"])),
+            "words added up front"
+        );
+        assert!(!snapshot.is_prefix_of(&words(&["fridge"])), "missing word");
+    }
+}
@@ -0,0 +1,265 @@
+use std::net::SocketAddr;
+use std::ops::ControlFlow;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use axum::Json;
+use axum::extract::ws::{Message, Utf8Bytes};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse as _, Response};
+use hashbrown::HashMap;
+use serde::Serialize;
+use tokio::sync::{broadcast, mpsc};
+use tracing::{Level, event};
+use uuid::Uuid;
+
+use crate::states::config::RateLimit;
+use crate::words::codec::Codec;
+use crate::words::connections::Connection;
+use crate::words::rate_limit::TokenBucket;
+use crate::words::{MoveEventParams, MoveOutcome, ServerMessage, WireWords, WsState, catch_up};
+
+/// Messages waiting to be sent to a single poet on SSE.
+const SSE_BUFFER: usize = 64;
+
+/// The poets on SSE on a fridge, by the id they move words with, and the moves they have left.
+#[derive(Default)]
+pub struct SsePoets {
+    moves: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl SsePoets {
+    fn join(&self, limit: RateLimit) -> String {
+        let poet = Uuid::now_v7().to_string();
+
+        self.moves
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(poet.clone(), TokenBucket::new(limit));
+
+        poet
+    }
+
+    fn leave(&self, poet: &str) {
+        self.moves
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(poet);
+    }
+
+    /// Takes a move from those left to `poet`, if `take_address_move` takes one from those left to
+    /// their address too. `None` when there's no such poet.
+    fn take_move(
+        &self,
+        poet: &str,
+        limit: RateLimit,
+        take_address_move: impl FnOnce() -> bool,
+    ) -> Option<bool> {
+        let mut moves = self.moves.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let bucket = moves.get_mut(poet)?;
+
+        // the limits may have been reloaded since the poet joined
+        bucket.set_limit(limit);
+
+        // the poet's move is only taken once their address has moves left, so it isn't lost for nothing
+        Some(bucket.can_take(1) && take_address_move() && bucket.try_take(1))
+    }
+}
+
+/// Feeds a poet on SSE the messages a poet on the websocket gets, as JSON, counting `connection`
+/// until they're gone. Returns the id they move words with, see [`submit_move`].
+///
+/// Poets who fall behind get a fresh copy of the fridge. The feed ends when we shut down,
+/// after which their `EventSource` reconnects.
+pub fn subscribe(
+    state: Arc<WsState>,
+    connection: Connection,
+) -> (String, mpsc::Receiver<Utf8Bytes>) {
+    let (tx, rx) = mpsc::channel(SSE_BUFFER);
+
+    let poet = state.sse_poets.join(state.move_limits().poet);
+
+    tokio::spawn({
+        let poet = poet.clone();
+
+        async move {
+            forward(&state, tx).await;
+
+            state.sse_poets.leave(&poet);
+
+            // counted until the poet is gone
+            drop(connection);
+        }
+    });
+
+    (poet, rx)
+}
+
+async fn forward(state: &WsState, tx: mpsc::Sender<Utf8Bytes>) {
+    // subscribe before taking the snapshot, so that no resize or move falls in between
+    let (mut broadcast_rx, sequence, _) = state.subscribe(None);
+
+    let config = ServerMessage::from(state.fridge_dimensions());
+
+    if send_json(&tx, &config).await.is_break()
+        || send_fridge(&tx, state).await.is_break()
+        || send_json(&tx, &sequence).await.is_break()
+    {
+        return;
+    }
+
+    event!(Level::DEBUG, fridge = state.name, "SSE client connected");
+
+    state.join();
+
+    loop {
+        let result = tokio::select! {
+            () = tx.closed() => break,
+            result = broadcast_rx.recv() => result,
+        };
+
+        // poets on SSE don't have a client id, so no message excludes them
+        let frame = match result {
+            Ok((_, frame)) => frame,
+            Err(broadcast::error::RecvError::Lagged(count)) => {
+                event!(Level::TRACE, count, "SSE client lagged, catching up");
+
+                let Some(messages) = catch_up(state, &mut broadcast_rx, Codec::Json).await else {
+                    break;
+                };
+
+                if send_text(&tx, messages).await.is_break() {
+                    break;
+                }
+
+                continue;
+            },
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+
+        let is_goodbye = matches!(frame.message(), &ServerMessage::Goodbye { .. });
+
+        // the same JSON the poets on the websocket get
+        let Some(Message::Text(json)) = frame.encoded(Codec::Json) else {
+            break;
+        };
+
+        if tx.send(json).await.is_err() || is_goodbye {
+            break;
+        }
+    }
+
+    state.leave();
+
+    event!(Level::TRACE, fridge = state.name, "SSE client disconnected");
+}
+
+/// Sends the words on the fridge, and which of them are being dragged.
+async fn send_fridge(tx: &mpsc::Sender<Utf8Bytes>, state: &WsState) -> ControlFlow<()> {
+    {
+        let words = state.word_list.read().await;
+
+        send_json(
+            tx,
+            &WireWords {
+                r#type: "words",
+                data: &words,
+            },
+        )
+        .await?;
+    }
+
+    for id in state.grabs.held_words() {
+        send_json(tx, &ServerMessage::Grabbed { id }).await?;
+    }
+
+    ControlFlow::Continue(())
+}
+
+/// Sends messages encoded as JSON, which are always text.
+async fn send_text(tx: &mpsc::Sender<Utf8Bytes>, messages: Vec<Message>) -> ControlFlow<()> {
+    for message in messages {
+        let Message::Text(json) = message else {
+            return ControlFlow::Break(());
+        };
+
+        if tx.send(json).await.is_err() {
+            return ControlFlow::Break(());
+        }
+    }
+
+    ControlFlow::Continue(())
+}
+
+async fn send_json<T>(tx: &mpsc::Sender<Utf8Bytes>, message: &T) -> ControlFlow<()>
+where
+    T: Serialize + ?Sized,
+{
+    let json = match serde_json::to_string(message) {
+        Ok(json) => json,
+        Err(error) => {
+            event!(
+                Level::ERROR,
+                ?error,
+                "failed to serialize message, this is a bug"
+            );
+            return ControlFlow::Break(());
+        },
+    };
+
+    if tx.send(Utf8Bytes::from(json)).await.is_err() {
+        return ControlFlow::Break(());
+    }
+
+    ControlFlow::Continue(())
+}
+
+/// Moves a word for `poet` on SSE, with the same checks and limits as a move over the websocket.
+///
+/// Like on the websocket, a refused move is answered with a `Correction`, as is one over the limits.
+pub async fn submit_move(
+    state: &WsState,
+    poet: &str,
+    address: SocketAddr,
+    move_event: MoveEventParams,
+) -> Response {
+    let taken = state
+        .sse_poets
+        .take_move(poet, state.move_limits().poet, || {
+            state.take_address_moves(address.ip(), 1)
+        });
+
+    match taken {
+        None => {
+            return (
+                StatusCode::FORBIDDEN,
+                "no such poet, listen to the events first",
+            )
+                .into_response();
+        },
+        Some(true) => {},
+        Some(false) => {
+            event!(Level::DEBUG, %address, "too many moves on SSE, dropping");
+
+            return match state.word(move_event.id).await {
+                Some(word) => (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    Json(ServerMessage::Correction(MoveEventParams::from(&word))),
+                )
+                    .into_response(),
+                None => (StatusCode::TOO_MANY_REQUESTS, "too many moves").into_response(),
+            };
+        },
+    }
+
+    match state.apply_move(move_event, None).await {
+        MoveOutcome::Moved(_) => StatusCode::NO_CONTENT.into_response(),
+        MoveOutcome::Stale(current) | MoveOutcome::Grabbed(current) => (
+            StatusCode::CONFLICT,
+            Json(ServerMessage::Correction(current)),
+        )
+            .into_response(),
+        MoveOutcome::OutOfBounds => (StatusCode::BAD_REQUEST, "out of bounds").into_response(),
+        MoveOutcome::UnknownWord => (StatusCode::NOT_FOUND, "no such word").into_response(),
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::VecDeque;
+
+use serde::Serialize;
+
+use crate::words::history::Position;
+
+/// How many of their own moves a poet can undo.
+pub const MAX_UNDO: usize = 100;
+
+/// One of a poet's own moves, which can be undone as long as nobody moved the word since.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Step {
+    pub id: usize,
+    pub from: Position,
+    pub to: Position,
+}
+
+impl Step {
+    fn reversed(self) -> Self {
+        Step {
+            id: self.id,
+            from: self.to,
+            to: self.from,
+        }
+    }
+}
+
+/// A poet's moves that can be undone, and the undone ones that can be redone.
+#[derive(Default)]
+pub struct UndoStack {
+    done: VecDeque<Step>,
+    undone: Vec<Step>,
+}
+
+impl UndoStack {
+    /// Records a move, after which whatever was undone can't be redone anymore.
+    pub fn push(&mut self, step: Step) {
+        if self.done.len() >= MAX_UNDO {
+            self.done.pop_front();
+        }
+
+        self.done.push_back(step);
+        self.undone.clear();
+    }
+
+    /// The move that undoes the latest move.
+    pub fn undo(&mut self) -> Option<Step> {
+        self.done.pop_back().map(Step::reversed)
+    }
+
+    /// Records that `undo`, as returned by [`UndoStack::undo`], was made.
+    pub fn undone(&mut self, undo: Step) {
+        self.undone.push(undo.reversed());
+    }
+
+    /// The move that redoes the latest undone move.
+    pub fn redo(&mut self) -> Option<Step> {
+        self.undone.pop()
+    }
+
+    /// Records that `redo`, as returned by [`UndoStack::redo`], was made.
+    pub fn redone(&mut self, redo: Step) {
+        self.done.push_back(redo);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::words::history::Position;
+    use crate::words::undo::{Step, UndoStack};
+
+    fn step(id: usize, from: u32, to: u32) -> Step {
+        Step {
+            id,
+            from: Position { x: from, y: from },
+            to: Position { x: to, y: to },
+        }
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut stack = UndoStack::default();
+
+        stack.push(step(1, 0, 1));
+        stack.push(step(2, 5, 6));
+
+        let undo = stack.undo().unwrap();
+        assert_eq!(undo, step(2, 6, 5));
+        stack.undone(undo);
+
+        let redo = stack.redo().unwrap();
+        assert_eq!(redo, step(2, 5, 6));
+        stack.redone(redo);
+
+        assert_eq!(stack.undo(), Some(step(2, 6, 5)));
+        assert_eq!(stack.undo(), Some(step(1, 1, 0)));
+        assert_eq!(stack.undo(), None);
+    }
+
+    #[test]
+    fn moving_forgets_what_was_undone() {
+        let mut stack = UndoStack::default();
+
+        stack.push(step(1, 0, 1));
+
+        let undo = stack.undo().unwrap();
+        stack.undone(undo);
+
+        stack.push(step(2, 5, 6));
+
+        assert_eq!(stack.redo(), None);
+    }
+}
@@ -0,0 +1,397 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{self, Context as _};
+use hashbrown::HashMap;
+
+/// The word list that ships with the binary. It predates repeat counts and repeats common tiles
+/// by listing them multiple times, so unlike the lists passed on the command line it's allowed to contain duplicates.
+const BUNDLED_WORD_LIST: &str = include_str!("../../../../assets/word-list-all.txt");
+
+/// The pack the bundled word list ends up in.
+const BUNDLED_PACK: &str = "classic";
+
+/// Longest word (in characters) we're willing to put on a tile.
+const MAX_WORD_LENGTH: usize = 32;
+
+/// Most tiles we'll make of a single word.
+const MAX_REPEAT: usize = 100;
+
+/// A themed set of words, each with the number of tiles to make of it.
+#[derive(Debug, PartialEq, Eq)]
+pub struct WordPack {
+    name: String,
+    words: Vec<(String, usize)>,
+}
+
+/// All the packs we know about, in the order they were first seen.
+#[derive(Debug)]
+pub struct WordPacks {
+    packs: Vec<WordPack>,
+}
+
+impl WordPacks {
+    /// The tiles for a fridge made of `pack_names`, in that order, or of all packs when `pack_names` is empty.
+    ///
+    /// # Errors
+    /// * One of the packs doesn't exist
+    pub fn tiles(&self, pack_names: &[String]) -> Result<Vec<String>, eyre::Report> {
+        let packs = if pack_names.is_empty() {
+            self.packs.iter().collect::<Vec<_>>()
+        } else {
+            pack_names
+                .iter()
+                .map(|pack_name| {
+                    self.packs
+                        .iter()
+                        .find(|pack| pack.name == *pack_name)
+                        .ok_or_else(|| {
+                            eyre::Report::msg(format!("Unknown word pack `{}`", pack_name))
+                        })
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        Ok(packs
+            .into_iter()
+            .flat_map(|pack| pack.words.iter())
+            .flat_map(|&(ref word, count)| std::iter::repeat_n(word, count))
+            .cloned()
+            .collect())
+    }
+}
+
+/// A word list file, before parsing.
+struct Source {
+    /// Where the list came from, for error messages.
+    name: String,
+    /// The pack words go in until the list says otherwise.
+    default_pack: String,
+    contents: String,
+}
+
+/// Loads the word packs from the given files, and from the `.txt` files in the given directories,
+/// or the bundled word list when no paths are given.
+///
+/// Every line is a word, optionally followed by `*<count>` to make more than one tile of it.
+/// Words go into the pack named after the file, unless they're under a `[<pack>]` line.
+///
+/// # Errors
+/// * A path couldn't be read
+/// * A word list contains empty lines, duplicates, oversize words, or invalid repeat counts or pack names
+pub async fn load_word_packs(paths: &[PathBuf]) -> Result<WordPacks, eyre::Report> {
+    if paths.is_empty() {
+        return Ok(WordPacks {
+            packs: vec![WordPack {
+                name: BUNDLED_PACK.to_owned(),
+                words: BUNDLED_WORD_LIST
+                    .lines()
+                    .map(|word| (word.to_owned(), 1))
+                    .collect(),
+            }],
+        });
+    }
+
+    let mut sources = Vec::new();
+
+    for path in paths {
+        read_path(path, &mut sources).await?;
+    }
+
+    parse_word_lists(&sources)
+}
+
+async fn read_path(path: &Path, sources: &mut Vec<Source>) -> Result<(), eyre::Report> {
+    let metadata = tokio::fs::metadata(path)
+        .await
+        .wrap_err_with(|| format!("Failed to read word list {}", path.display()))?;
+
+    if !metadata.is_dir() {
+        return read_file(path, sources).await;
+    }
+
+    let mut entries = tokio::fs::read_dir(path)
+        .await
+        .wrap_err_with(|| format!("Failed to read word list directory {}", path.display()))?;
+
+    let mut files = Vec::new();
+
+    while let Some(entry) = entries.next_entry().await? {
+        let file_path = entry.path();
+
+        if file_path.extension() == Some(OsStr::new("txt")) && !entry.file_type().await?.is_dir() {
+            files.push(file_path);
+        }
+    }
+
+    if files.is_empty() {
+        return Err(eyre::Report::msg(format!(
+            "Word list directory {} contains no .txt files",
+            path.display()
+        )));
+    }
+
+    // directory order isn't stable, and word ids depend on the order
+    files.sort();
+
+    for file in files {
+        read_file(&file, sources).await?;
+    }
+
+    Ok(())
+}
+
+async fn read_file(path: &Path, sources: &mut Vec<Source>) -> Result<(), eyre::Report> {
+    let contents = tokio::fs::read_to_string(path)
+        .await
+        .wrap_err_with(|| format!("Failed to read word list {}", path.display()))?;
+
+    let default_pack = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    sources.push(Source {
+        name: path.display().to_string(),
+        default_pack,
+        contents,
+    });
+
+    Ok(())
+}
+
+/// Pack names end up in configuration, separated by `,`, `+` and `=`, so we keep them boring.
+fn is_valid_pack_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Splits `word*count` into the word and its count, which defaults to 1.
+fn parse_line(line: &str) -> Result<(&str, usize), String> {
+    let Some((word, count)) = line.rsplit_once('*') else {
+        return Ok((line, 1));
+    };
+
+    // a word that merely contains a `*`
+    if word.is_empty() || count.is_empty() || !count.bytes().all(|b| b.is_ascii_digit()) {
+        return Ok((line, 1));
+    }
+
+    match count.parse::<usize>() {
+        Ok(count @ 1..=MAX_REPEAT) => Ok((word, count)),
+        Ok(_) | Err(_) => Err(format!(
+            "`{}` has a repeat count outside of 1 to {}",
+            word, MAX_REPEAT
+        )),
+    }
+}
+
+/// Parses the sources into packs, reporting every problem at once.
+fn parse_word_lists(sources: &[Source]) -> Result<WordPacks, eyre::Report> {
+    let mut packs = Vec::<WordPack>::new();
+    // per pack, where we first saw each word
+    let mut seen = HashMap::<String, HashMap<&str, (&str, usize)>>::new();
+    let mut problems = Vec::new();
+
+    for source in sources {
+        let mut pack_name = source.default_pack.as_str();
+
+        for (index, line) in source.contents.lines().enumerate() {
+            let line_number = index + 1;
+
+            if line.trim().is_empty() {
+                problems.push(format!("{}:{}: empty line", source.name, line_number));
+                continue;
+            }
+
+            if let Some(name) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                if !is_valid_pack_name(name) {
+                    problems.push(format!(
+                        "{}:{}: `{}` is not a valid pack name",
+                        source.name, line_number, name
+                    ));
+                }
+
+                pack_name = name;
+                continue;
+            }
+
+            let (word, count) = match parse_line(line) {
+                Ok(parsed) => parsed,
+                Err(problem) => {
+                    problems.push(format!("{}:{}: {}", source.name, line_number, problem));
+                    continue;
+                },
+            };
+
+            if word.chars().count() > MAX_WORD_LENGTH {
+                problems.push(format!(
+                    "{}:{}: `{}` is longer than {} characters",
+                    source.name, line_number, word, MAX_WORD_LENGTH
+                ));
+                continue;
+            }
+
+            let seen_in_pack = seen.entry_ref(pack_name).or_default();
+
+            if let Some(&(first_name, first_line_number)) = seen_in_pack.get(word) {
+                problems.push(format!(
+                    "{}:{}: `{}` is a duplicate of {}:{}, use `{}*<count>` for more tiles",
+                    source.name, line_number, word, first_name, first_line_number, word
+                ));
+                continue;
+            }
+
+            seen_in_pack.insert(word, (&source.name, line_number));
+
+            let index = if let Some(index) = packs.iter().position(|pack| pack.name == pack_name) {
+                index
+            } else {
+                packs.push(WordPack {
+                    name: pack_name.to_owned(),
+                    words: Vec::new(),
+                });
+
+                packs.len() - 1
+            };
+
+            packs[index].words.push((word.to_owned(), count));
+        }
+
+        if !is_valid_pack_name(&source.default_pack) && seen.contains_key(&source.default_pack) {
+            problems.push(format!(
+                "{}: `{}` is not a valid pack name, put the words under a `[<pack>]` line",
+                source.name, source.default_pack
+            ));
+        }
+    }
+
+    if !problems.is_empty() {
+        return Err(eyre::Report::msg(format!(
+            "Invalid word list:\n{}",
+            problems.join("\n")
+        )));
+    }
+
+    if packs.is_empty() {
+        return Err(eyre::Report::msg("Word lists contain no words"));
+    }
+
+    Ok(WordPacks { packs })
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::words::word_list::{Source, WordPack, parse_word_lists};
+
+    fn sources(lists: &[(&str, &str)]) -> Vec<Source> {
+        lists
+            .iter()
+            .map(|&(name, contents)| Source {
+                name: format!("{}.txt", name),
+                default_pack: name.to_owned(),
+                contents: contents.to_owned(),
+            })
+            .collect()
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|&name| name.to_owned()).collect()
+    }
+
+    #[test]
+    fn parses_multiple_lists_in_order() {
+        let packs =
+            parse_word_lists(&sources(&[("a", "fridge\nmagnet\n"), ("b", "poem")])).unwrap();
+
+        assert_eq!(packs.tiles(&[]).unwrap(), ["fridge", "magnet", "poem"]);
+    }
+
+    #[test]
+    fn parses_packs_and_repeat_counts() {
+        let packs = parse_word_lists(&sources(&[(
+            "basics",
+            "the*3\na\n[love]\nheart*2\nthe\n[geek]\ncompiler\n5*2",
+        )]))
+        .unwrap();
+
+        assert_eq!(
+            packs.packs,
+            [
+                WordPack {
+                    name: "basics".to_owned(),
+                    words: vec![("the".to_owned(), 3), ("a".to_owned(), 1)],
+                },
+                WordPack {
+                    name: "love".to_owned(),
+                    words: vec![("heart".to_owned(), 2), ("the".to_owned(), 1)],
+                },
+                WordPack {
+                    name: "geek".to_owned(),
+                    words: vec![("compiler".to_owned(), 1), ("5".to_owned(), 2)],
+                },
+            ]
+        );
+
+        assert_eq!(
+            packs.tiles(&names(&["geek", "love"])).unwrap(),
+            ["compiler", "5", "5", "heart", "heart", "the"]
+        );
+    }
+
+    #[test]
+    fn words_containing_asterisks() {
+        let packs = parse_word_lists(&sources(&[("a", "*\n**\nfoo*bar\n*2")])).unwrap();
+
+        assert_eq!(packs.tiles(&[]).unwrap(), ["*", "**", "foo*bar", "*2"]);
+    }
+
+    #[test]
+    fn rejects_unknown_packs() {
+        let packs = parse_word_lists(&sources(&[("a", "fridge")])).unwrap();
+
+        let error = packs.tiles(&names(&["a", "b"])).unwrap_err().to_string();
+
+        assert_eq!(error, "Unknown word pack `b`");
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        let error = parse_word_lists(&sources(&[
+            ("a", "fridge\n\nmagnet"),
+            (
+                "b",
+                "magnet\nsupercalifragilisticexpialidocious-ish\nthe*0\n[not a pack]\nword",
+            ),
+            ("c", "[a]\nfridge"),
+        ]))
+        .unwrap_err()
+        .to_string();
+
+        assert_eq!(
+            error,
+            "Invalid word list:\n\
+             a.txt:2: empty line\n\
+             b.txt:2: `supercalifragilisticexpialidocious-ish` is longer than 32 characters\n\
+             b.txt:3: `the` has a repeat count outside of 1 to 100\n\
+             b.txt:4: `not a pack` is not a valid pack name\n\
+             c.txt:2: `fridge` is a duplicate of a.txt:1, use `fridge*<count>` for more tiles"
+        );
+    }
+
+    #[test]
+    fn rejects_empty_lists() {
+        let error = parse_word_lists(&sources(&[("a", "")]))
+            .unwrap_err()
+            .to_string();
+
+        assert_eq!(error, "Word lists contain no words");
+    }
+}
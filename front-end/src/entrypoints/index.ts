@@ -1,21 +1,67 @@
-import { State } from "../lib/state";
+import { setupUndo } from "../lib/handlers";
+import { SseConnection } from "../lib/sse-connection";
+import { type Connection, State } from "../lib/state";
 import { WebSocketHandler } from "../lib/web-socket-handler";
 
 import "bootstrap";
 
-function connect(): void {
+// protocol version, has to match the server's
+const PROTOCOL_VERSION = 2;
+
+// kept across reconnects, so that we can resume where we left off
+let session: { handler: WebSocketHandler; state: State } | undefined;
+
+// returns a function that cleans up after the connection is gone
+function start(connection: Connection): () => void {
+    if (session === undefined) {
+        const state = new State(connection, PROTOCOL_VERSION);
+
+        session = { handler: new WebSocketHandler(state), state };
+    } else {
+        session.state.socket = connection;
+    }
+
+    session.handler.init();
+
+    return setupUndo(session.state);
+}
+
+function webSocketUrl(): string {
     const protocol = location.protocol === "https:" ? "wss:" : "ws:";
-    const ws = new WebSocket(`${protocol}//${location.host}/ws`);
+    // passing on the query string lets `?replay=true&speed=10` turn the page into a replay viewer
+    const url = new URL(`${protocol}//${location.host}/ws${location.search}`);
+
+    // the server sends only what we missed, or the whole fridge when it doesn't remember that far back
+    const sequence = session?.state.sequence;
+
+    if (sequence !== undefined) {
+        url.searchParams.set("stream", sequence.stream);
+        url.searchParams.set("seq", sequence.seq.toString(10));
+    }
+
+    return url.toString();
+}
+
+function connect(): void {
+    const ws = new WebSocket(webSocketUrl());
+
+    let opened = false;
 
     ws.addEventListener("open", () => {
-        const state = new State(ws, 1);
-        const handler = new WebSocketHandler(state);
+        opened = true;
 
-        handler.init();
+        const removeUndo = start(ws);
+
+        ws.addEventListener("close", removeUndo);
     });
 
     ws.addEventListener("close", () => {
-        setTimeout(connect, 2000);
+        if (opened) {
+            setTimeout(connect, 2000);
+        } else {
+            // we never got through, something in between probably blocks websockets
+            connectSse();
+        }
     });
 
     ws.addEventListener("error", () => {
@@ -23,4 +69,17 @@ function connect(): void {
     });
 }
 
+function connectSse(): void {
+    const source = new EventSource(`/api/events${location.search}`);
+
+    // the EventSource reconnects by itself, after which the server sends the whole fridge again
+    source.addEventListener(
+        "open",
+        () => {
+            start(new SseConnection(source));
+        },
+        { once: true },
+    );
+}
+
 connect();
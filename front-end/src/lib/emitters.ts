@@ -7,16 +7,60 @@ export interface Move {
     y: number;
 }
 
+export function sendUndo(state: State, count: number): void {
+    const message: ClientMessage = { type: "undo", data: { count } };
+
+    state.socket.send(JSON.stringify(message));
+}
+
+export function sendRedo(state: State, count: number): void {
+    const message: ClientMessage = { type: "redo", data: { count } };
+
+    state.socket.send(JSON.stringify(message));
+}
+
+export function sendGrab(state: State, id: string): void {
+    const message: ClientMessage = { type: "grab", data: { id: Number(id.slice(2)) } };
+
+    state.socket.send(JSON.stringify(message));
+}
+
+// the server sends drags out 20 times a second, there's no use in sending more
+const DRAG_INTERVAL_MS = 50;
+
+let lastDragSent = 0;
+
+export function sendDrag(state: State, { id, x, y }: Move): void {
+    const now = performance.now();
+
+    if (now - lastDragSent < DRAG_INTERVAL_MS) {
+        return;
+    }
+
+    lastDragSent = now;
+
+    const message: ClientMessage = { type: "drag", data: { id: Number(id.slice(2)), x, y } };
+
+    state.socket.send(JSON.stringify(message));
+}
+
 export function sendMove(state: State, { id, x, y }: Move): void {
+    const wordId = Number(id.slice(2));
+    const v = state.wordVersions.get(wordId) ?? 0;
+
     const message: ClientMessage = {
         type: "move",
         data: {
-            id: Number(id.slice(2)),
-            v: state.version,
+            id: wordId,
+            v,
             x,
             y,
         },
     };
 
     state.socket.send(JSON.stringify(message));
+
+    // if the server accepts the move, this is the version it'll give the word,
+    // if it doesn't, it sends a correction
+    state.wordVersions.set(wordId, v + 1);
 }
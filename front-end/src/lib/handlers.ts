@@ -1,4 +1,4 @@
-import { sendMove } from "./emitters";
+import { sendDrag, sendGrab, sendMove, sendRedo, sendUndo } from "./emitters";
 import { pixelToCoordinate } from "./shared";
 import type { State } from "./state";
 import { outerHeight, outerWidth, toHtmlWordId } from "./utilities";
@@ -20,6 +20,13 @@ export function setupMovable(state: State, element: HTMLElement): void {
     let lastScrollY = 0;
 
     function mouseDown(event: MouseEvent): void {
+        // someone else is dragging it
+        if (element.classList.contains("grabbed")) {
+            return;
+        }
+
+        sendGrab(state, element.id);
+
         // get initial mousedown coordinated
         const mouseX = event.clientX;
         const mouseY = event.clientY;
@@ -81,6 +88,15 @@ export function setupMovable(state: State, element: HTMLElement): void {
     function mouseMove(event: MouseEvent): void {
         // get new mouse coordinates
         moveElement(event.clientX, event.clientY);
+
+        sendDrag(state, { id: element.id, ...abstractPosition() });
+    }
+
+    function abstractPosition(): { x: number; y: number } {
+        return {
+            x: Math.round(pixelToCoordinate(newLeft, outerWidth(element), state.fridgeWidth)),
+            y: Math.round(pixelToCoordinate(newTop, outerHeight(element), state.fridgeHeight)),
+        };
     }
 
     function moveElement(newMouseX: number, newMouseY: number): void {
@@ -130,15 +146,38 @@ export function setupMovable(state: State, element: HTMLElement): void {
         document.removeEventListener("mouseup", mouseUp);
         document.removeEventListener("scroll", scroll);
 
-        const abstractX = Math.round(pixelToCoordinate(newLeft, outerWidth(element), state.fridgeWidth));
-        const abstractY = Math.round(pixelToCoordinate(newTop, outerHeight(element), state.fridgeHeight));
-
-        sendMove(state, { id: element.id, x: abstractX, y: abstractY });
+        sendMove(state, { id: element.id, ...abstractPosition() });
     }
 
     element.addEventListener("mousedown", mouseDown);
 }
 
+// ctrl+z undoes our last move, ctrl+shift+z or ctrl+y redoes it
+// returns a function that removes the listener again
+export function setupUndo(state: State): () => void {
+    function keyDown(event: KeyboardEvent): void {
+        if (!event.ctrlKey && !event.metaKey) {
+            return;
+        }
+
+        const key = event.key.toLowerCase();
+
+        if (key === "z" && !event.shiftKey) {
+            event.preventDefault();
+            sendUndo(state, 1);
+        } else if (key === "y" || (key === "z" && event.shiftKey)) {
+            event.preventDefault();
+            sendRedo(state, 1);
+        }
+    }
+
+    document.addEventListener("keydown", keyDown);
+
+    return () => {
+        document.removeEventListener("keydown", keyDown);
+    };
+}
+
 export function purgeWords(wordIds: number[]): void {
     for (const id of wordIds) {
         const htmlId = `#${toHtmlWordId(id)}`;
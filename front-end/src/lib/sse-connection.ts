@@ -0,0 +1,46 @@
+import type { Connection } from "./state";
+import type { ClientMessage } from "./types";
+
+// for when websockets are blocked: we listen over server-sent events, and send moves over plain HTTP
+// everything else we'd send (grabs, drags, pongs, undo) only works over a websocket, and is dropped
+export class SseConnection implements Connection {
+    private readonly source: EventSource;
+    // who we move words as, the server names us anew every time the EventSource (re)connects
+    private poet = "";
+
+    public constructor(source: EventSource) {
+        this.source = source;
+
+        this.source.addEventListener("poet", (event: MessageEvent<string>) => {
+            this.poet = event.data;
+        });
+    }
+
+    public addEventListener(type: "message", listener: (event: MessageEvent<string>) => void): void {
+        this.source.addEventListener(type, listener);
+    }
+
+    public send(data: string): void {
+        // oxlint-disable-next-line typescript/no-unsafe-type-assertion -- we built it ourselves
+        const message = JSON.parse(data) as ClientMessage;
+
+        if (message.type !== "move") {
+            return;
+        }
+
+        const url = new URL("/api/events/move", location.origin);
+        url.search = location.search;
+        url.searchParams.set("poet", this.poet);
+
+        void fetch(url, {
+            method: "POST",
+            headers: { "content-type": "application/json" },
+            body: JSON.stringify(message.data),
+        }).then(async (response) => {
+            // a refused move, or one too many, is answered with a correction, which we handle like one that came in over the stream
+            if (response.status === 409 || response.status === 429) {
+                this.source.dispatchEvent(new MessageEvent("message", { data: await response.text() }));
+            }
+        });
+    }
+}
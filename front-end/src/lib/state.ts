@@ -1,14 +1,27 @@
+import type { Sequence } from "./types";
+
+// what we need from the connection to the server, a websocket, or an SseConnection when those are blocked
+export interface Connection {
+    addEventListener(type: "message", listener: (event: MessageEvent<string>) => void): void;
+    send(data: string): void;
+}
+
 export class State {
     public fridgeHeight: number;
     public fridgeWidth: number;
     public poets: number;
-    public socket: WebSocket;
+    // unset until the server tells us where we are
+    public sequence: Sequence | undefined;
+    public socket: Connection;
     public readonly version: number;
+    // the version of each word we last heard of, moves are made against it
+    public readonly wordVersions = new Map<number, number>();
 
-    public constructor(socket: WebSocket, version: number) {
+    public constructor(socket: Connection, version: number) {
         this.socket = socket;
         this.version = version;
         this.poets = 0;
+        this.sequence = undefined;
         this.fridgeWidth = 0;
         this.fridgeHeight = 0;
     }
@@ -1,5 +1,6 @@
 export interface Word {
     id: number;
+    v: number;
     word: string;
     x: number;
     y: number;
@@ -19,6 +20,12 @@ export interface Hup {
     v: number;
 }
 
+// where we are in the fridge's numbered messages, sent back when reconnecting so we only get what we missed
+export interface Sequence {
+    seq: number;
+    stream: string;
+}
+
 export interface MoveEventParameters {
     id: number;
     v: number;
@@ -26,12 +33,33 @@ export interface MoveEventParameters {
     y: number;
 }
 
-export type ServerMessage =
+export interface DragEventParameters {
+    id: number;
+    x: number;
+    y: number;
+}
+
+// messages needed to get the fridge right carry a sequence number
+export type ServerMessage = { seq?: number } & (
     | { data: Config; type: "config" }
     | { data: Hup; type: "hup" }
     | { data: MoveEventParameters; type: "move" }
+    | { data: MoveEventParameters; type: "correction" }
+    | { data: DragEventParameters; type: "drag" }
+    | { data: { id: number }; type: "grabbed" }
+    | { data: { id: number }; type: "released" }
     | { data: Poets; type: "poets" }
     | { data: Record<string, never>; type: "goodbye" }
-    | { data: Word[]; type: "words" };
+    | { data: Sequence; type: "sequence" }
+    | { data: Word[]; type: "words" }
+    | { data: Word[]; type: "added" }
+);
 
-export type ClientMessage = { data: { id: number }; type: "pong" } | { data: MoveEventParameters; type: "move" };
+export type ClientMessage =
+    | { data: { id: number }; type: "grab" }
+    | { data: { id: number }; type: "pong" }
+    | { data: { id: number }; type: "release" }
+    | { data: { count: number }; type: "redo" }
+    | { data: { count: number }; type: "undo" }
+    | { data: DragEventParameters; type: "drag" }
+    | { data: MoveEventParameters; type: "move" };
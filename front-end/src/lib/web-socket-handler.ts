@@ -1,7 +1,7 @@
 import { purgeWords, setupMovable } from "./handlers";
 import { coordinateToPixel } from "./shared";
 import type { State } from "./state";
-import type { ClientMessage, Config, Hup, MoveEventParameters, Poets, ServerMessage, Word } from "./types";
+import type { ClientMessage, Config, DragEventParameters, Hup, MoveEventParameters, Poets, ServerMessage, Word } from "./types";
 import { outerHeight, outerWidth, reload, toHtmlWordId } from "./utilities";
 
 export class WebSocketHandler {
@@ -28,6 +28,10 @@ export class WebSocketHandler {
                 return;
             }
 
+            if (message.seq !== undefined && this.state.sequence !== undefined) {
+                this.state.sequence.seq = message.seq;
+            }
+
             switch (message.type) {
                 case "config": {
                     this.onConfig(message.data);
@@ -37,14 +41,31 @@ export class WebSocketHandler {
                     this.onWords(message.data);
                     break;
                 }
+                case "added": {
+                    this.onAdded(message.data);
+                    break;
+                }
                 case "poets": {
                     this.onPoets(message.data);
                     break;
                 }
-                case "move": {
+                case "move":
+                case "correction": {
                     this.onMove(message.data);
                     break;
                 }
+                case "drag": {
+                    this.onDrag(message.data);
+                    break;
+                }
+                case "grabbed": {
+                    this.onGrabbed(message.data.id, true);
+                    break;
+                }
+                case "released": {
+                    this.onGrabbed(message.data.id, false);
+                    break;
+                }
                 case "hup": {
                     this.onHup(message.data);
                     break;
@@ -52,6 +73,10 @@ export class WebSocketHandler {
                 case "goodbye": {
                     break;
                 }
+                case "sequence": {
+                    this.state.sequence = message.data;
+                    break;
+                }
             }
         });
     }
@@ -83,7 +108,24 @@ export class WebSocketHandler {
         this.state.socket.send(JSON.stringify(pong));
     }
 
-    public onMove({ id, x, y }: MoveEventParameters): void {
+    public onDrag({ id, x, y }: DragEventParameters): void {
+        const element: HTMLElement | null = document.querySelector(`#${toHtmlWordId(id)}`);
+
+        if (element !== null) {
+            // glide between the positions, which arrive every 50ms
+            element.style.setProperty("transition", "left 50ms linear, top 50ms linear");
+            element.style.setProperty("left", `${coordinateToPixel(x, outerWidth(element), this.state.fridgeWidth)}px`);
+            element.style.setProperty("top", `${coordinateToPixel(y, outerHeight(element), this.state.fridgeHeight)}px`);
+        }
+    }
+
+    public onGrabbed(id: number, grabbed: boolean): void {
+        document.querySelector(`#${toHtmlWordId(id)}`)?.classList.toggle("grabbed", grabbed);
+    }
+
+    public onMove({ id, v, x, y }: MoveEventParameters): void {
+        this.state.wordVersions.set(id, v);
+
         const time = 1500;
         const wordHtmlId = `#${toHtmlWordId(id)}`;
 
@@ -128,18 +170,26 @@ export class WebSocketHandler {
     }
 
     public onWords(words: Word[]): void {
+        purgeWords(this.wordIds);
+
+        // clear array, nasty, but this is how JavaScript wants to do it
+        this.wordIds.length = 0;
+
+        this.state.wordVersions.clear();
+
+        this.onAdded(words);
+    }
+
+    // words put on the fridge after the others, leaving those alone
+    public onAdded(words: Word[]): void {
         const fridge = document.querySelector("#fridge");
 
         if (fridge === null) {
             return;
         }
 
-        purgeWords(this.wordIds);
-
-        // clear array, nasty, but this is how JavaScript wants to do it
-        this.wordIds.length = 0;
-
         for (const word of words) {
+            this.state.wordVersions.set(word.id, word.v);
             addWord(this.state, fridge, word);
             this.wordIds.push(word.id);
         }